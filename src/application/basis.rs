@@ -0,0 +1,143 @@
+use bytemuck::{Pod, Zeroable};
+use egui::{Color32, Stroke};
+use vek::{Mat4, Vec2, Vec3};
+
+use super::{camera::Camera, gizmo::HANDLE_RADIUS, linalg, matrix_grid::matrix_value_grid, overlay::Overlay, stack::Convention};
+
+const BASIS_COLORS: [Color32; 3] = [
+    Color32::from_rgb(255, 140, 140),
+    Color32::from_rgb(140, 255, 140),
+    Color32::from_rgb(140, 140, 255),
+];
+
+// mirrors the Basis uniform block in grid.frag
+#[repr(C)]
+#[derive(Copy, Clone, Zeroable, Pod)]
+pub struct BasisData {
+    to_basis: Mat4<f32>,
+    info: [f32; 4], // enabled, line scale, unused, unused
+}
+
+// expresses the stack in a custom basis B, as B⁻¹MB
+pub struct ChangeOfBasis {
+    pub enabled: bool,
+    vectors: [Vec3<f32>; 3],
+    dragged: Option<usize>, // basis vector whose tip is being dragged
+}
+
+impl ChangeOfBasis {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            vectors: [Vec3::new(1.0, 0.5, 0.0), Vec3::new(-0.25, 1.0, 0.0), Vec3::unit_z()],
+            dragged: None,
+        }
+    }
+
+    // the basis vectors as columns
+    pub fn matrix(&self) -> Mat4<f32> {
+        let mut m = Mat4::identity();
+        for (col, v) in self.vectors.iter().enumerate() {
+            for row in 0..3 {
+                m[(row, col)] = v[row];
+            }
+        }
+        m
+    }
+
+    // None while the basis is (nearly) degenerate
+    pub fn inverse(&self) -> Option<Mat4<f32>> {
+        linalg::invert(&self.matrix()).inverse
+    }
+
+    // M expressed in the basis
+    pub fn conjugate(&self, m: &Mat4<f32>) -> Option<Mat4<f32>> {
+        self.inverse().map(|inverse| inverse * *m * self.matrix())
+    }
+
+    pub fn data(&self) -> BasisData {
+        let inverse = self.inverse();
+        let line_scale = (self.vectors[0].xy().magnitude() + self.vectors[1].xy().magnitude()) / 2.0;
+        BasisData {
+            to_basis: inverse.unwrap_or(Mat4::identity()),
+            info: [(self.enabled && inverse.is_some()) as u8 as f32, line_scale.max(f32::EPSILON), 0.0, 0.0],
+        }
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.dragged.is_some()
+    }
+
+    pub fn release(&mut self) {
+        self.dragged = None;
+    }
+
+    // pick up the tip of a basis vector under the cursor, given in physical pixels
+    pub fn grab(&mut self, mouse: Vec2<f32>, camera: &Camera, view_size: Vec2<f32>, pixels_per_point: f32) {
+        if !self.enabled {
+            return;
+        }
+        let radius = HANDLE_RADIUS * pixels_per_point;
+        self.dragged = self.vectors[..2]
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i, camera.world_to_screen(v.xy(), view_size).distance(mouse)))
+            .filter(|(_, distance)| *distance <= radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i);
+    }
+
+    pub fn drag(&mut self, mouse: Vec2<f32>, camera: &Camera, view_size: Vec2<f32>) {
+        if let Some(i) = self.dragged {
+            let world = camera.screen_to_world(mouse, view_size);
+            self.vectors[i].x = world.x;
+            self.vectors[i].y = world.y;
+        }
+    }
+
+    // edit the basis, and show the product of the stack in both bases
+    pub fn gui(&mut self, ui: &mut egui::Ui, product: &Mat4<f32>, show_full_matrix: bool, convention: Convention) {
+        ui.label("Drag the tips of the basis vectors on the canvas, or edit them here:");
+        let count = if show_full_matrix { 3 } else { 2 };
+        egui::Grid::new("basis_vectors").show(ui, |ui| {
+            for (i, v) in self.vectors.iter_mut().enumerate().take(count) {
+                ui.colored_label(BASIS_COLORS[i], format!("b{}", i + 1));
+                ui.add(egui::DragValue::new(&mut v.x).speed(0.01).prefix("x: "));
+                ui.add(egui::DragValue::new(&mut v.y).speed(0.01).prefix("y: "));
+                if show_full_matrix {
+                    ui.add(egui::DragValue::new(&mut v.z).speed(0.01).prefix("z: "));
+                }
+                ui.end_row();
+            }
+        });
+        if ui.button("Reset to standard basis").clicked() {
+            self.vectors = [Vec3::unit_x(), Vec3::unit_y(), Vec3::unit_z()];
+        }
+        let Some(conjugate) = self.conjugate(product) else {
+            ui.colored_label(ui.visuals().warn_fg_color, "The basis vectors are (nearly) linearly dependent");
+            return;
+        };
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.vertical(|ui| {
+                ui.label("M");
+                matrix_value_grid(ui, "basis_standard", product, show_full_matrix, convention.row_vectors);
+            });
+            ui.separator();
+            ui.vertical(|ui| {
+                ui.label(if convention.row_vectors { "BMB⁻¹" } else { "B⁻¹MB" });
+                matrix_value_grid(ui, "basis_custom", &conjugate, show_full_matrix, convention.row_vectors);
+            });
+        });
+    }
+
+    pub fn draw(&self, overlay: &Overlay) {
+        let painter = &overlay.painter;
+        let origin = overlay.to_screen(Vec2::zero());
+        for (v, color) in self.vectors[..2].iter().zip(BASIS_COLORS) {
+            let tip = overlay.to_screen(v.xy());
+            painter.arrow(origin, tip - origin, Stroke::new(2.0, color));
+            painter.circle_filled(tip, HANDLE_RADIUS, color);
+        }
+    }
+}
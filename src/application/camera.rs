@@ -1,5 +1,5 @@
 use bytemuck::{Pod, Zeroable};
-use vek::{FrustumPlanes, Mat4, Vec2};
+use vek::{FrustumPlanes, Mat4, Vec2, Vec4};
 
 use crate::application::input::Input;
 
@@ -39,6 +39,19 @@ impl Camera {
         proj * scale * translation
     }
 
+    // convert a position in physical pixels to world space
+    pub fn screen_to_world(&self, screen: Vec2<f32>, window_size: Vec2<f32>) -> Vec2<f32> {
+        let ndc = Vec2::new(screen.x / window_size.x * 2.0 - 1.0, 1.0 - screen.y / window_size.y * 2.0);
+        let inverse = self.get_matrix(window_size.x / window_size.y).inverted();
+        (inverse * Vec4::new(ndc.x, ndc.y, 0.0, 1.0)).xy()
+    }
+
+    // convert a world space position to physical pixels
+    pub fn world_to_screen(&self, world: Vec2<f32>, window_size: Vec2<f32>) -> Vec2<f32> {
+        let ndc = self.get_matrix(window_size.x / window_size.y) * Vec4::new(world.x, world.y, 0.0, 1.0);
+        Vec2::new((ndc.x + 1.0) / 2.0 * window_size.x, (1.0 - ndc.y) / 2.0 * window_size.y)
+    }
+
     pub fn pan_and_zoom_data(&self, aspect: f32) -> PanAndZoom {
         PanAndZoom {
             position: self.position,
@@ -0,0 +1,137 @@
+use egui::{Color32, Stroke};
+use vek::{Mat4, Vec2};
+
+use super::{linalg::Complex, overlay::Overlay, warp::draw_curve};
+
+const SOURCE_COLOR: Color32 = Color32::from_rgb(150, 150, 150);
+const IMAGE_COLOR: Color32 = Color32::from_rgb(255, 200, 80);
+
+// (az + b) / (cz + d)
+pub fn mobius(a: Complex, b: Complex, c: Complex, d: Complex, z: Complex) -> Complex {
+    (a * z + b) / (c * z + d)
+}
+
+pub fn is_degenerate(a: Complex, b: Complex, c: Complex, d: Complex) -> bool {
+    (a * d - b * c).abs() < 1e-9
+}
+
+// z -> wz as a rotation-scaling matrix
+pub fn multiplication_matrix(w: Complex) -> Mat4<f32> {
+    let mut m = Mat4::identity();
+    m[(0, 0)] = w.re as f32;
+    m[(0, 1)] = -w.im as f32;
+    m[(1, 0)] = w.im as f32;
+    m[(1, 1)] = w.re as f32;
+    m
+}
+
+// edit a complex number as two drag values
+pub fn complex_drag(ui: &mut egui::Ui, label: &str, z: &mut Complex) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.add(egui::DragValue::new(&mut z.re).speed(0.01));
+        ui.label("+");
+        ui.add(egui::DragValue::new(&mut z.im).speed(0.01).suffix("i"));
+    });
+}
+
+// circles and lines, which möbius maps send to circles and lines
+#[derive(Clone, Copy)]
+enum GeneralizedCircle {
+    Circle { center: Vec2<f32>, radius: f32 },
+    Line { point: Vec2<f32>, angle: f32 },
+}
+
+impl GeneralizedCircle {
+    fn samples(&self) -> Vec<Vec2<f32>> {
+        const SAMPLES: usize = 360;
+        match *self {
+            GeneralizedCircle::Circle { center, radius } => (0..=SAMPLES)
+                .map(|i| {
+                    let t = i as f32 / SAMPLES as f32 * std::f32::consts::TAU;
+                    center + Vec2::new(t.cos(), t.sin()) * radius
+                })
+                .collect(),
+            // spread the samples along the whole line, so its far ends show up as well
+            GeneralizedCircle::Line { point, angle } => (1..SAMPLES)
+                .map(|i| {
+                    let t = (i as f32 / SAMPLES as f32 - 0.5) * std::f32::consts::PI;
+                    point + Vec2::new(angle.cos(), angle.sin()) * t.tan()
+                })
+                .collect(),
+        }
+    }
+}
+
+// treats the plane as ℂ, and shows how circles and lines are mapped
+pub struct ComplexPlane {
+    pub enabled: bool,
+    circles: Vec<GeneralizedCircle>,
+}
+
+impl ComplexPlane {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            circles: vec![
+                GeneralizedCircle::Circle { center: Vec2::zero(), radius: 1.0 },
+                GeneralizedCircle::Circle { center: Vec2::new(1.0, 0.5), radius: 0.5 },
+                GeneralizedCircle::Line { point: Vec2::new(0.5, 0.0), angle: std::f32::consts::FRAC_PI_2 },
+            ],
+        }
+    }
+
+    pub fn gui(&mut self, ui: &mut egui::Ui) {
+        ui.label("The plane is ℂ, with x the real and y the imaginary part.");
+        ui.label("Möbius maps send circles and lines (grey) to circles and lines (yellow).");
+        let mut remove = None;
+        egui::Grid::new("generalized_circles").show(ui, |ui| {
+            for (n, circle) in self.circles.iter_mut().enumerate() {
+                match circle {
+                    GeneralizedCircle::Circle { center, radius } => {
+                        ui.label("Circle");
+                        ui.add(egui::DragValue::new(&mut center.x).speed(0.01).prefix("x: "));
+                        ui.add(egui::DragValue::new(&mut center.y).speed(0.01).prefix("y: "));
+                        ui.add(egui::DragValue::new(radius).speed(0.01).range(0.0..=f32::INFINITY).prefix("r: "));
+                    }
+                    GeneralizedCircle::Line { point, angle } => {
+                        ui.label("Line");
+                        ui.add(egui::DragValue::new(&mut point.x).speed(0.01).prefix("x: "));
+                        ui.add(egui::DragValue::new(&mut point.y).speed(0.01).prefix("y: "));
+                        ui.drag_angle(angle);
+                    }
+                }
+                if ui.button("Remove").clicked() {
+                    remove = Some(n);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(n) = remove {
+            self.circles.remove(n);
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Add circle").clicked() {
+                self.circles.push(GeneralizedCircle::Circle { center: Vec2::zero(), radius: 0.5 });
+            }
+            if ui.button("Add line").clicked() {
+                self.circles.push(GeneralizedCircle::Line { point: Vec2::zero(), angle: 0.0 });
+            }
+        });
+    }
+
+    pub fn draw(&self, overlay: &Overlay, map: impl Fn(Vec2<f32>) -> Vec2<f32>) {
+        for circle in &self.circles {
+            let samples = circle.samples();
+            draw_curve(overlay, samples.iter().copied(), Stroke::new(1.0, SOURCE_COLOR));
+            draw_curve(overlay, samples.into_iter().map(&map), Stroke::new(2.0, IMAGE_COLOR));
+        }
+        let painter = &overlay.painter;
+        let font = egui::FontId::proportional(14.0);
+        painter.text(overlay.to_screen(Vec2::new(1.0, 0.0)), egui::Align2::LEFT_TOP, "1", font.clone(), Color32::WHITE);
+        painter.text(overlay.to_screen(Vec2::new(0.0, 1.0)), egui::Align2::LEFT_TOP, "i", font.clone(), Color32::WHITE);
+        let (_, max) = overlay.visible_world();
+        painter.text(overlay.to_screen(Vec2::new(max.x, 0.0)), egui::Align2::RIGHT_BOTTOM, "Re", font.clone(), Color32::WHITE);
+        painter.text(overlay.to_screen(Vec2::new(0.0, max.y)), egui::Align2::LEFT_TOP, "Im", font, Color32::WHITE);
+    }
+}
@@ -0,0 +1,165 @@
+use egui::{Color32, Stroke};
+use vek::Vec2;
+
+use super::{camera::Camera, gizmo::HANDLE_RADIUS, mesh, overlay::Overlay};
+
+const OUTLINE_COLOR: Color32 = Color32::from_rgb(255, 255, 255);
+const INVALID_COLOR: Color32 = Color32::from_rgb(255, 60, 60);
+const DOUBLE_CLICK_TIME: f32 = 0.3; // seconds
+
+// a polygon outlined by clicking on the canvas, which can then be used as the model
+pub struct PolygonTool {
+    pub enabled: bool,
+    pub placing: bool, // clicks on the canvas place vertices
+    snap: bool,
+    snap_step: f32,
+    points: Vec<Vec2<f32>>,
+    closed: bool,
+    dragged: Option<usize>,
+    last_click: Option<(instant::Instant, bool)>, // and whether it added a vertex
+}
+
+impl PolygonTool {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            placing: false,
+            snap: true,
+            snap_step: 0.1,
+            points: Vec::new(),
+            closed: false,
+            dragged: None,
+            last_click: None,
+        }
+    }
+
+    fn snapped(&self, world: Vec2<f32>) -> Vec2<f32> {
+        if self.snap { (world / self.snap_step).round() * self.snap_step } else { world }
+    }
+
+    // the finished outline, if it can be triangulated
+    pub fn polygon(&self) -> Option<&[Vec2<f32>]> {
+        (self.closed && mesh::triangulate(&self.points).is_some()).then_some(&self.points[..])
+    }
+
+    // clicks add vertices and a double click finishes the outline. returns true if there is a new polygon to use.
+    // the camera tells how close together two vertices are on the screen
+    pub fn click(&mut self, world: Vec2<f32>, camera: &Camera, view_size: Vec2<f32>, pixels_per_point: f32) -> bool {
+        let now = instant::Instant::now();
+        let double_click = self.last_click.is_some_and(|(last, _)| (now - last).as_secs_f32() < DOUBLE_CLICK_TIME);
+        if double_click && !self.closed {
+            // the first click of the double click added a vertex of its own, which goes again if it sits on the previous one
+            let added = self.last_click.is_some_and(|(_, added)| added);
+            let radius = HANDLE_RADIUS * pixels_per_point;
+            let to_screen = |p: Vec2<f32>| camera.world_to_screen(p, view_size);
+            let spurious = added && matches!(self.points[..], [.., previous, last] if to_screen(previous).distance(to_screen(last)) <= radius);
+            if self.points.len() - spurious as usize >= 3 {
+                if spurious {
+                    self.points.pop();
+                }
+                self.closed = true;
+                self.placing = false;
+                self.last_click = None;
+                return self.polygon().is_some();
+            }
+        }
+        // clicking after the polygon was closed starts a new one
+        if self.closed {
+            self.points.clear();
+            self.closed = false;
+        }
+        let point = self.snapped(world);
+        let added = self.points.last() != Some(&point);
+        if added {
+            self.points.push(point);
+        }
+        self.last_click = Some((now, added));
+        false
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.dragged.is_some()
+    }
+
+    pub fn release(&mut self) {
+        self.dragged = None;
+    }
+
+    // pick up the vertex under the cursor, given in physical pixels
+    pub fn grab(&mut self, mouse: Vec2<f32>, camera: &Camera, view_size: Vec2<f32>, pixels_per_point: f32) {
+        // while outlining, clicks on a vertex are still clicks
+        if !self.enabled || (self.placing && !self.closed) {
+            return;
+        }
+        let radius = HANDLE_RADIUS * pixels_per_point;
+        self.dragged = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i, camera.world_to_screen(*p, view_size).distance(mouse)))
+            .filter(|(_, distance)| *distance <= radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i);
+    }
+
+    // returns true if a vertex moved
+    pub fn drag(&mut self, mouse: Vec2<f32>, camera: &Camera, view_size: Vec2<f32>) -> bool {
+        let Some(i) = self.dragged else {
+            return false;
+        };
+        let point = self.snapped(camera.screen_to_world(mouse, view_size));
+        let moved = self.points[i] != point;
+        self.points[i] = point;
+        moved
+    }
+
+    // returns true if the vertices were edited
+    pub fn gui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.checkbox(&mut self.placing, "Place vertices on the canvas");
+        if self.placing {
+            ui.label(if self.closed { "Click to start a new polygon." } else { "Click to add a vertex, double click to close the polygon." });
+        }
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.snap, "Snap to grid");
+            ui.add_enabled(self.snap, egui::DragValue::new(&mut self.snap_step).speed(0.01).range(0.01..=10.0).prefix("step: "));
+        });
+        if self.closed && self.polygon().is_none() {
+            ui.colored_label(INVALID_COLOR, "The outline crosses itself, so it can't be filled.");
+        }
+        let mut changed = false;
+        let mut remove = None;
+        egui::Grid::new("polygon_vertices").striped(true).show(ui, |ui| {
+            for (i, point) in self.points.iter_mut().enumerate() {
+                ui.label(format!("{}", i + 1));
+                changed |= ui.add(egui::DragValue::new(&mut point.x).speed(0.01).prefix("x: ")).changed();
+                changed |= ui.add(egui::DragValue::new(&mut point.y).speed(0.01).prefix("y: ")).changed();
+                if ui.button("Remove").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove {
+            self.points.remove(i);
+            changed = true;
+        }
+        if ui.button("Clear").clicked() {
+            self.points.clear();
+            self.closed = false;
+        }
+        changed && self.closed
+    }
+
+    pub fn draw(&self, overlay: &Overlay) {
+        let painter = &overlay.painter;
+        let color = if !self.closed || self.polygon().is_some() { OUTLINE_COLOR } else { INVALID_COLOR };
+        let mut outline = self.points.iter().map(|p| overlay.to_screen(*p)).collect::<Vec<_>>();
+        if self.closed {
+            outline.extend(outline.first().copied());
+        }
+        painter.line(outline.clone(), Stroke::new(1.5, color));
+        for point in outline {
+            painter.circle_stroke(point, HANDLE_RADIUS, Stroke::new(1.5, color));
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use egui::{Color32, Stroke};
+use vek::{Mat4, Vec2, Vec4};
+
+use super::{linalg, overlay::Overlay};
+
+#[derive(PartialEq, Clone, Copy)]
+enum VectorField {
+    Off,
+    Displacement, // (M - I)x, how far a point moves in one step
+    Image,        // Mx, where a point ends up
+}
+
+// iterates the composed transform on seed points (x_{n+1} = M x_n) and draws the
+// phase portrait of the continuous system x' = Ax, where A is the affine part of M
+pub struct DynamicalSystem {
+    pub enabled: bool,
+    pub seeds: Vec<Vec2<f32>>,
+    iterations: usize,
+    field: VectorField,
+    field_density: usize, // arrows along the short side of the screen
+    streamlines: bool,
+}
+
+const ORBIT_COLOR: Color32 = Color32::from_rgb(255, 170, 0);
+const FIELD_COLOR: Color32 = Color32::from_rgb(90, 160, 255);
+const STREAMLINE_COLOR: Color32 = Color32::from_rgb(120, 220, 140);
+const FIXED_POINT_COLOR: Color32 = Color32::from_rgb(255, 80, 200);
+
+// points further out than this are considered to have escaped
+const ESCAPE_RADIUS: f32 = 1e6;
+
+impl DynamicalSystem {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            seeds: vec![Vec2::new(1.0, 0.5)],
+            iterations: 20,
+            field: VectorField::Off,
+            field_density: 16,
+            streamlines: false,
+        }
+    }
+
+    fn orbit(&self, transform: &Mat4<f32>, seed: Vec2<f32>) -> Vec<Vec2<f32>> {
+        let mut orbit = vec![seed];
+        let mut x = seed;
+        for _ in 0..self.iterations {
+            x = linalg::transform_point_2d(transform, x);
+            if !x.x.is_finite() || !x.y.is_finite() || x.magnitude() > ESCAPE_RADIUS {
+                break;
+            }
+            orbit.push(x);
+        }
+        orbit
+    }
+
+    // x' = Ax + b for the continuous system
+    fn velocity(transform: &Mat4<f32>, x: Vec2<f32>) -> Vec2<f32> {
+        (*transform * Vec4::new(x.x, x.y, 0.0, 1.0)).xy()
+    }
+
+    // trace the flow through a point by integrating the normalized velocity with rk4,
+    // so all streamlines have comparable lengths regardless of speed
+    fn streamline(transform: &Mat4<f32>, seed: Vec2<f32>, step: f32, steps: usize) -> Vec<Vec2<f32>> {
+        let direction = |x: Vec2<f32>| {
+            let v = Self::velocity(transform, x);
+            let speed = v.magnitude();
+            if speed > 1e-6 { v / speed } else { Vec2::zero() }
+        };
+        let mut line = vec![seed];
+        let mut x = seed;
+        for _ in 0..steps {
+            let k1 = direction(x);
+            let k2 = direction(x + k1 * step / 2.0);
+            let k3 = direction(x + k2 * step / 2.0);
+            let k4 = direction(x + k3 * step);
+            let delta = (k1 + k2 * 2.0 + k3 * 2.0 + k4) * step / 6.0;
+            if delta.magnitude() < step.abs() * 1e-3 {
+                break;
+            }
+            x += delta;
+            line.push(x);
+        }
+        line
+    }
+
+    pub fn gui(&mut self, ui: &mut egui::Ui, transform: &Mat4<f32>) {
+        ui.label("Right-click the canvas to add a seed point.");
+        ui.horizontal(|ui| {
+            ui.label(format!("{} seeds", self.seeds.len()));
+            if ui.button("Clear seeds").clicked() {
+                self.seeds.clear();
+            }
+        });
+        ui.add(egui::Slider::new(&mut self.iterations, 0..=200).text("Iterations"));
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Vector field:");
+            ui.selectable_value(&mut self.field, VectorField::Off, "Off");
+            ui.selectable_value(&mut self.field, VectorField::Displacement, "(M - I)x");
+            ui.selectable_value(&mut self.field, VectorField::Image, "Mx");
+        });
+        ui.add(egui::Slider::new(&mut self.field_density, 4..=48).text("Density"));
+        ui.checkbox(&mut self.streamlines, "Streamlines of x' = Ax");
+        ui.separator();
+
+        // stability of the linear part
+        let eigenvalues = linalg::eigenvalues_2d(transform);
+        ui.label(format!("Eigenvalues: {}, {}", eigenvalues[0], eigenvalues[1]));
+        let spectral_radius = eigenvalues.iter().map(|l| l.abs()).fold(0.0, f64::max);
+        let discrete = if (spectral_radius - 1.0).abs() < 1e-6 {
+            "neutral"
+        } else if spectral_radius < 1.0 {
+            "stable"
+        } else {
+            "unstable"
+        };
+        ui.label(format!("Iterated map: spectral radius {spectral_radius:.3}, {discrete}"));
+        let max_real = eigenvalues.iter().map(|l| l.re).fold(f64::NEG_INFINITY, f64::max);
+        let continuous = if max_real.abs() < 1e-6 {
+            "neutral"
+        } else if max_real < 0.0 {
+            "stable"
+        } else {
+            "unstable"
+        };
+        ui.label(format!("Flow: largest real part {max_real:.3}, {continuous}"));
+        match Self::fixed_point(transform) {
+            Some(p) => ui.label(format!("Fixed point of the map: ({:.3}, {:.3})", p.x, p.y)),
+            None => ui.label("The map has no unique fixed point"),
+        };
+        match Self::equilibrium(transform) {
+            Some(p) => ui.label(format!("Equilibrium of the flow: ({:.3}, {:.3})", p.x, p.y)),
+            None => ui.label("The flow has no unique equilibrium"),
+        };
+    }
+
+    // solves Mx = x, i.e. (A - I)x = -b
+    fn fixed_point(transform: &Mat4<f32>) -> Option<Vec2<f32>> {
+        let m = transform;
+        linalg::solve_2d(m[(0, 0)] - 1.0, m[(0, 1)], m[(1, 0)], m[(1, 1)] - 1.0, -Vec2::new(m[(0, 3)], m[(1, 3)]))
+    }
+
+    // solves Ax + b = 0
+    fn equilibrium(transform: &Mat4<f32>) -> Option<Vec2<f32>> {
+        let m = transform;
+        linalg::solve_2d(m[(0, 0)], m[(0, 1)], m[(1, 0)], m[(1, 1)], -Vec2::new(m[(0, 3)], m[(1, 3)]))
+    }
+
+    pub fn draw(&self, overlay: &Overlay, transform: &Mat4<f32>) {
+        let painter = &overlay.painter;
+        let (min, max) = overlay.visible_world();
+        let spacing = (max - min).reduce_partial_min() / self.field_density as f32;
+
+        if self.field != VectorField::Off {
+            // scale arrows so the longest one is about one grid spacing long
+            let mut arrows = Vec::new();
+            let mut y = (min.y / spacing).floor() * spacing;
+            while y <= max.y {
+                let mut x = (min.x / spacing).floor() * spacing;
+                while x <= max.x {
+                    let p = Vec2::new(x, y);
+                    let image = linalg::transform_point_2d(transform, p);
+                    let v = match self.field {
+                        VectorField::Displacement => image - p,
+                        _ => image,
+                    };
+                    if v.x.is_finite() && v.y.is_finite() {
+                        arrows.push((p, v));
+                    }
+                    x += spacing;
+                }
+                y += spacing;
+            }
+            let longest = arrows.iter().map(|(_, v)| v.magnitude()).fold(0.0, f32::max);
+            if longest > 0.0 {
+                let scale = 0.9 * spacing / longest;
+                for (p, v) in arrows {
+                    let origin = overlay.to_screen(p);
+                    let tip = overlay.to_screen(p + v * scale);
+                    painter.arrow(origin, tip - origin, Stroke::new(1.0, FIELD_COLOR));
+                }
+            }
+        }
+
+        if self.streamlines {
+            let step = spacing / 8.0;
+            let steps = (self.field_density * 8 * 4).min(2000);
+            let mut y = (min.y / (2.0 * spacing)).floor() * 2.0 * spacing + spacing;
+            while y <= max.y {
+                let mut x = (min.x / (2.0 * spacing)).floor() * 2.0 * spacing + spacing;
+                while x <= max.x {
+                    // trace both ways from the seed so it ends up in the middle of the line
+                    let seed = Vec2::new(x, y);
+                    let mut line = Self::streamline(transform, seed, -step, steps / 2);
+                    line.reverse();
+                    line.extend(Self::streamline(transform, seed, step, steps / 2).into_iter().skip(1));
+                    let points = line.iter().map(|p| overlay.to_screen(*p)).collect::<Vec<_>>();
+                    if let [.., before, last] = points[..] {
+                        let direction = (last - before).normalized() * 20.0;
+                        if direction.is_finite() {
+                            painter.arrow(last - direction, direction, Stroke::new(1.0, STREAMLINE_COLOR));
+                        }
+                    }
+                    painter.line(points, Stroke::new(1.0, STREAMLINE_COLOR));
+                    x += 2.0 * spacing;
+                }
+                y += 2.0 * spacing;
+            }
+        }
+
+        for seed in &self.seeds {
+            let orbit = self.orbit(transform, *seed);
+            let points = orbit.iter().map(|p| overlay.to_screen(*p)).collect::<Vec<_>>();
+            painter.line(points.clone(), Stroke::new(1.5, ORBIT_COLOR));
+            for (i, point) in points.into_iter().enumerate() {
+                let radius = if i == 0 { 4.0 } else { 2.5 };
+                painter.circle_filled(point, radius, ORBIT_COLOR);
+            }
+        }
+
+        if let Some(p) = Self::fixed_point(transform) {
+            painter.circle_stroke(overlay.to_screen(p), 5.0, Stroke::new(2.0, FIXED_POINT_COLOR));
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use vek::Mat4;
+
+use super::{linalg, stack::Convention};
+
+// the kinds of code a matrix can be copied as
+#[derive(Clone, Copy, PartialEq)]
+pub enum CodeFormat {
+    Latex,
+    NumPy,
+    Matlab,
+    Glsl,
+    Wgsl,
+    Vek,
+    Glam,
+    Json,
+}
+
+impl CodeFormat {
+    pub fn all() -> [Self; 8] {
+        [Self::Latex, Self::NumPy, Self::Matlab, Self::Glsl, Self::Wgsl, Self::Vek, Self::Glam, Self::Json]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Latex => "LaTeX (bmatrix)",
+            Self::NumPy => "NumPy",
+            Self::Matlab => "MATLAB/Octave",
+            Self::Glsl => "GLSL mat4",
+            Self::Wgsl => "WGSL mat4x4f",
+            Self::Vek => "Rust vek::Mat4",
+            Self::Glam => "Rust glam::Mat4",
+            Self::Json => "JSON (rows)",
+        }
+    }
+
+    // whether the elements are passed column by column instead of row by row
+    fn column_major(&self) -> bool {
+        matches!(self, Self::Glsl | Self::Wgsl | Self::Glam)
+    }
+}
+
+// a number with a fixed amount of decimals. code wants float literals even for whole numbers,
+// and rounding shouldn't leave a stray minus sign on zero
+fn number(value: f32, precision: usize, float_literal: bool) -> String {
+    let mut text = format!("{value:.precision$}");
+    if text.parse::<f32>() == Ok(0.0) {
+        text = text.trim_start_matches('-').to_string();
+    }
+    if float_literal && !text.contains('.') {
+        text.push_str(".0");
+    }
+    text
+}
+
+// the matrix as code for the format, with the elements in the order the target expects them
+pub fn matrix_code(mat: &Mat4<f32>, format: CodeFormat, precision: usize) -> String {
+    let float_literal = !matches!(format, CodeFormat::Latex | CodeFormat::Matlab);
+    // rows of the matrix, or its columns for column-major targets
+    let lines: Vec<Vec<String>> = (0..4)
+        .map(|i| {
+            (0..4)
+                .map(|j| {
+                    let value = if format.column_major() { mat[(j, i)] } else { mat[(i, j)] };
+                    number(value, precision, float_literal)
+                })
+                .collect()
+        })
+        .collect();
+    let joined = |separator: &str, prefix: &str, suffix: &str| -> Vec<String> {
+        lines.iter().map(|line| format!("{prefix}{}{suffix}", line.join(separator))).collect()
+    };
+    match format {
+        CodeFormat::Latex => format!("\\begin{{bmatrix}}\n{}\n\\end{{bmatrix}}", joined(" & ", "    ", "").join(" \\\\\n")),
+        CodeFormat::NumPy => format!("np.array([\n{}\n])", joined(", ", "    [", "]").join(",\n")),
+        CodeFormat::Matlab => format!("[{}]", joined(" ", "", "").join(";\n ")),
+        CodeFormat::Glsl => format!("mat4(\n{}\n)", joined(", ", "    ", "").join(",\n")),
+        CodeFormat::Wgsl => format!("mat4x4f(\n{}\n)", joined(", ", "    ", "").join(",\n")),
+        // vek takes the elements in row order whatever its layout
+        CodeFormat::Vek => format!("Mat4::new(\n{}\n)", joined(", ", "    ", "").join(",\n")),
+        CodeFormat::Glam => format!("Mat4::from_cols_array(&[\n{}\n])", joined(", ", "    ", "").join(",\n")),
+        CodeFormat::Json => format!("[\n{}\n]", joined(", ", "  [", "]").join(",\n")),
+    }
+}
+
+// buttons copying a matrix to the clipboard, as it is shown under the convention.
+// infinities and nan aren't valid in most of the formats, so matrices holding them can't be copied
+pub fn copy_menu(ui: &mut egui::Ui, mat: &Mat4<f32>, convention: Convention, precision: &mut usize) {
+    ui.add(egui::DragValue::new(precision).range(0..=9).prefix("Decimals: "));
+    ui.separator();
+    let finite = linalg::is_finite(mat);
+    if !finite {
+        ui.colored_label(ui.visuals().warn_fg_color, "The matrix has infinite or undefined elements");
+    }
+    let shown = if convention.row_vectors { mat.transposed() } else { *mat };
+    for format in CodeFormat::all() {
+        let code = matrix_code(&shown, format, *precision);
+        if ui.add_enabled(finite, egui::Button::new(format.name())).on_hover_ui(|ui| { ui.monospace(&code); }).clicked() {
+            ui.ctx().copy_text(code);
+            ui.close();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_finite_matrices_can_be_copied() {
+        let mut mat = Mat4::<f32>::identity();
+        assert!(linalg::is_finite(&mat));
+        for value in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            mat[(2, 3)] = value;
+            assert!(!linalg::is_finite(&mat), "{value} counted as finite");
+        }
+        // a huge but finite result still can
+        mat[(2, 3)] = f32::MAX;
+        assert!(linalg::is_finite(&mat));
+    }
+
+    #[test]
+    fn code_formats_order_elements() {
+        let mat = Mat4::new(1.0, 2.0, 0.0, 3.0, 0.0, 1.0, 0.0, -0.0001, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0);
+        assert_eq!(matrix_code(&mat, CodeFormat::Matlab, 2), "[1.00 2.00 0.00 3.00;\n 0.00 1.00 0.00 0.00;\n 0.00 0.00 1.00 0.00;\n 0.00 0.00 0.00 1.00]");
+        // column major, and whole numbers stay float literals
+        assert!(matrix_code(&mat, CodeFormat::Glsl, 0).starts_with("mat4(\n    1.0, 0.0, 0.0, 0.0,\n    2.0, 1.0, 0.0, 0.0,"));
+    }
+}
@@ -0,0 +1,295 @@
+use vek::Vec2;
+
+// a parsed arithmetic expression in x and y
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    X,
+    Y,
+    Negate(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Exp,
+    Ln,
+    Sqrt,
+    Abs,
+    Atan2,
+    Pow,
+    Min,
+    Max,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<(Function, usize)> {
+        Some(match name {
+            "sin" => (Function::Sin, 1),
+            "cos" => (Function::Cos, 1),
+            "tan" => (Function::Tan, 1),
+            "exp" => (Function::Exp, 1),
+            "ln" | "log" => (Function::Ln, 1),
+            "sqrt" => (Function::Sqrt, 1),
+            "abs" => (Function::Abs, 1),
+            "atan2" => (Function::Atan2, 2),
+            "pow" => (Function::Pow, 2),
+            "min" => (Function::Min, 2),
+            "max" => (Function::Max, 2),
+            _ => return None,
+        })
+    }
+
+    fn apply(self, args: &[f64]) -> f64 {
+        match self {
+            Function::Sin => args[0].sin(),
+            Function::Cos => args[0].cos(),
+            Function::Tan => args[0].tan(),
+            Function::Exp => args[0].exp(),
+            Function::Ln => args[0].ln(),
+            Function::Sqrt => args[0].sqrt(),
+            Function::Abs => args[0].abs(),
+            Function::Atan2 => args[0].atan2(args[1]),
+            Function::Pow => args[0].powf(args[1]),
+            Function::Min => args[0].min(args[1]),
+            Function::Max => args[0].max(args[1]),
+        }
+    }
+}
+
+impl Expr {
+    fn eval(&self, x: f64, y: f64) -> f64 {
+        match self {
+            Expr::Number(n) => *n,
+            Expr::X => x,
+            Expr::Y => y,
+            Expr::Negate(e) => -e.eval(x, y),
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (a.eval(x, y), b.eval(x, y));
+                match op {
+                    '+' => a + b,
+                    '-' => a - b,
+                    '*' => a * b,
+                    '/' => a / b,
+                    _ => a.powf(b),
+                }
+            }
+            Expr::Call(function, args) => {
+                let args = args.iter().map(|a| a.eval(x, y)).collect::<Vec<_>>();
+                function.apply(&args)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Symbol(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek() {
+                // allow exponents like 1e-3
+                let exponent_sign = (c == '-' || c == '+') && number.ends_with(['e', 'E']);
+                if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign {
+                    number.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Number(number.parse().map_err(|_| format!("Invalid number \"{number}\""))?));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_alphanumeric() || c == '_' {
+                    name.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Name(name));
+        } else if "+-*/^(),".contains(c) {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        } else {
+            return Err(format!("Unexpected character '{c}'"));
+        }
+    }
+    Ok(tokens)
+}
+
+// recursive descent over the usual precedence levels, ^ binds tightest and is right associative
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        if self.eat(symbol) { Ok(()) } else { Err(format!("Expected '{symbol}'")) }
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut expr = self.product()?;
+        loop {
+            let op = if self.eat('+') { '+' } else if self.eat('-') { '-' } else { return Ok(expr) };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        loop {
+            let op = if self.eat('*') { '*' } else if self.eat('/') { '/' } else { return Ok(expr) };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat('-') {
+            Ok(Expr::Negate(Box::new(self.unary()?)))
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.atom()?;
+        if self.eat('^') {
+            // -x^2 is -(x^2), but 2^-1 is allowed
+            Ok(Expr::Binary('^', Box::new(base), Box::new(self.unary()?)))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Symbol('(')) => {
+                let expr = self.sum()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Some(Token::Name(name)) => match name.as_str() {
+                "x" => Ok(Expr::X),
+                "y" => Ok(Expr::Y),
+                "pi" => Ok(Expr::Number(std::f64::consts::PI)),
+                "e" => Ok(Expr::Number(std::f64::consts::E)),
+                _ => {
+                    let (function, arity) = Function::from_name(&name).ok_or(format!("Unknown name \"{name}\""))?;
+                    self.expect('(')?;
+                    let mut args = vec![self.sum()?];
+                    while self.eat(',') {
+                        args.push(self.sum()?);
+                    }
+                    self.expect(')')?;
+                    if args.len() != arity {
+                        return Err(format!("{name} takes {arity} argument(s), got {}", args.len()));
+                    }
+                    Ok(Expr::Call(function, args))
+                }
+            },
+            Some(Token::Symbol(c)) => Err(format!("Unexpected '{c}'")),
+            None => Err("Unexpected end of expression".into()),
+        }
+    }
+}
+
+fn parse(source: &str) -> Result<Expr, String> {
+    let mut parser = Parser { tokens: tokenize(source)?, position: 0 };
+    let expr = parser.sum()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(_) => Err("Unexpected text after the expression".into()),
+    }
+}
+
+// a map of the plane given by an expression for each coordinate
+#[derive(Debug, Clone, PartialEq)]
+pub struct WarpFunction {
+    pub x: String,
+    pub y: String,
+    parsed: Result<(Expr, Expr), String>,
+}
+
+impl WarpFunction {
+    pub fn new(x: &str, y: &str) -> Self {
+        let mut f = Self {
+            x: x.into(),
+            y: y.into(),
+            parsed: Err(String::new()),
+        };
+        f.reparse();
+        f
+    }
+
+    // call after editing the expressions
+    pub fn reparse(&mut self) {
+        self.parsed = parse(&self.x)
+            .map_err(|e| format!("x': {e}"))
+            .and_then(|x| Ok((x, parse(&self.y).map_err(|e| format!("y': {e}"))?)));
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.parsed.as_ref().err().map(|e| e.as_str())
+    }
+
+    // leaves points alone while the expressions don't parse
+    pub fn eval(&self, p: Vec2<f32>) -> Vec2<f32> {
+        match &self.parsed {
+            Ok((x, y)) => {
+                let (px, py) = (p.x as f64, p.y as f64);
+                Vec2::new(x.eval(px, py) as f32, y.eval(px, py) as f32)
+            }
+            Err(_) => p,
+        }
+    }
+}
+
+pub fn presets() -> Vec<(&'static str, &'static str, &'static str)> {
+    vec![
+        ("Polar to cartesian", "x * cos(y)", "x * sin(y)"),
+        ("Sine warp", "x + 0.1 * sin(4 * y)", "y + 0.1 * sin(4 * x)"),
+        ("Swirl", "x * cos(sqrt(x^2 + y^2)) - y * sin(sqrt(x^2 + y^2))", "x * sin(sqrt(x^2 + y^2)) + y * cos(sqrt(x^2 + y^2))"),
+        ("Square z²", "x^2 - y^2", "2 * x * y"),
+        ("Exponential eᶻ", "exp(x) * cos(y)", "exp(x) * sin(y)"),
+        ("Shear by height", "x + y^2", "y"),
+    ]
+}
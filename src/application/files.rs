@@ -0,0 +1,177 @@
+#[cfg(target_arch = "wasm32")]
+use std::{cell::RefCell, rc::Rc};
+
+// a file the user handed us, by name and contents
+pub struct PickedFile {
+    pub name: String,
+    pub bytes: Vec<u8>,
+    pub path: Option<String>, // only natively, for files that refer to others next to them
+}
+
+fn has_extension(name: &str, extensions: &[&str]) -> bool {
+    let name = name.to_lowercase();
+    extensions.iter().any(|extension| name.ends_with(&format!(".{extension}")))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_path(path: &str) -> Result<PickedFile, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Couldn't read \"{path}\": {e}"))?;
+    let name = std::path::Path::new(path).file_name().map_or(path.into(), |name| name.to_string_lossy().into_owned());
+    Ok(PickedFile { name, bytes, path: Some(path.into()) })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_path(path: &str, bytes: &[u8]) -> Result<(), String> {
+    std::fs::write(path, bytes).map_err(|e| format!("Couldn't write \"{path}\": {e}"))
+}
+
+// lets the user pick files with the given extensions: a path natively, a file input on the web,
+// and files dropped onto the window on both
+pub struct FilePicker {
+    extensions: &'static [&'static str],
+    #[cfg(not(target_arch = "wasm32"))]
+    path: String,
+    #[cfg(target_arch = "wasm32")]
+    picked: Rc<RefCell<Option<PickedFile>>>,
+}
+
+impl FilePicker {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            #[cfg(not(target_arch = "wasm32"))]
+            path: String::new(),
+            #[cfg(target_arch = "wasm32")]
+            picked: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    pub fn gui(&mut self, ui: &mut egui::Ui) -> Option<Result<PickedFile, String>> {
+        let mut picked = None;
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.path).hint_text("path/to/file").desired_width(200.0));
+            if ui.button("Load").clicked() {
+                picked = Some(read_path(&self.path));
+            }
+        });
+        #[cfg(target_arch = "wasm32")]
+        {
+            if ui.button("Choose file…").clicked() {
+                let accept = self.extensions.iter().map(|extension| format!(".{extension}")).collect::<Vec<_>>().join(",");
+                open_file_input(&accept, self.picked.clone());
+            }
+            // the file is read asynchronously, and shows up in a later frame
+            if let Some(file) = self.picked.borrow_mut().take() {
+                picked = Some(Ok(file));
+            }
+        }
+        // the web backend of winit doesn't pass dropped files on
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.label(format!("Or drop a .{} file onto the window.", self.extensions.join("/.")));
+        let dropped = ui.ctx().input(|i| i.raw.dropped_files.clone());
+        for file in dropped.into_iter().filter(|file| has_extension(&file.name, self.extensions) || file.path.as_ref().is_some_and(|p| has_extension(&p.to_string_lossy(), self.extensions))) {
+            picked = Some(match (file.bytes, file.path) {
+                (Some(bytes), _) => Ok(PickedFile { name: file.name, bytes: bytes.to_vec(), path: None }),
+                #[cfg(not(target_arch = "wasm32"))]
+                (None, Some(path)) => read_path(&path.to_string_lossy()),
+                _ => Err(format!("Couldn't read the dropped file \"{}\"", file.name)),
+            });
+        }
+        picked
+    }
+}
+
+// saves files the app made: to a path natively, as a download on the web
+pub struct FileSaver {
+    #[cfg(not(target_arch = "wasm32"))]
+    path: String,
+    #[cfg(target_arch = "wasm32")]
+    name: String,
+}
+
+impl FileSaver {
+    pub fn new(name: &str) -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            path: name.into(),
+            #[cfg(target_arch = "wasm32")]
+            name: name.into(),
+        }
+    }
+
+    // the contents are only made when the user saves. returns what happened
+    pub fn gui(&mut self, ui: &mut egui::Ui, contents: impl FnOnce() -> Vec<u8>) -> Option<Result<String, String>> {
+        let mut saved = None;
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.path).hint_text("path/to/file").desired_width(200.0));
+            if ui.button("Save").clicked() {
+                saved = Some(write_path(&self.path, &contents()).map(|_| format!("Saved {}", self.path)));
+            }
+        });
+        #[cfg(target_arch = "wasm32")]
+        if ui.button("Download").clicked() {
+            saved = Some(download(&self.name, &contents()).map(|_| format!("Downloaded {}", self.name)));
+        }
+        saved
+    }
+}
+
+// a temporary link to the bytes that is clicked right away
+#[cfg(target_arch = "wasm32")]
+fn download(name: &str, bytes: &[u8]) -> Result<(), String> {
+    use wasm_bindgen::JsCast;
+    let error = |_| format!("Couldn't download \"{name}\"");
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let blob = web_sys::Blob::new_with_u8_array_sequence(&parts).map_err(error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(error)?;
+    let document = web_sys::window().and_then(|window| window.document()).ok_or_else(|| format!("Couldn't download \"{name}\""))?;
+    let anchor = document.create_element("a").map_err(error)?.unchecked_into::<web_sys::HtmlAnchorElement>();
+    anchor.set_href(&url);
+    anchor.set_download(name);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url).map_err(error)
+}
+
+// a hidden <input type="file"> that is clicked right away, the chosen file ends up in `picked`
+#[cfg(target_arch = "wasm32")]
+fn open_file_input(accept: &str, picked: Rc<RefCell<Option<PickedFile>>>) {
+    use wasm_bindgen::{closure::Closure, JsCast};
+    let Some(document) = web_sys::window().and_then(|window| window.document()) else {
+        return;
+    };
+    let Ok(input) = document.create_element("input").map(|element| element.unchecked_into::<web_sys::HtmlInputElement>()) else {
+        return;
+    };
+    input.set_type("file");
+    input.set_accept(accept);
+    let onchange = Closure::once_into_js(move |event: web_sys::Event| {
+        let Some(file) = event
+            .target()
+            .and_then(|target| target.dyn_into::<web_sys::HtmlInputElement>().ok())
+            .and_then(|input| input.files())
+            .and_then(|files| files.get(0))
+        else {
+            return;
+        };
+        let Ok(reader) = web_sys::FileReader::new() else {
+            return;
+        };
+        let name = file.name();
+        let onload = Closure::once_into_js(move |event: web_sys::Event| {
+            let result = event
+                .target()
+                .and_then(|target| target.dyn_into::<web_sys::FileReader>().ok())
+                .and_then(|reader| reader.result().ok());
+            if let Some(buffer) = result {
+                let bytes = js_sys::Uint8Array::new(&buffer).to_vec();
+                *picked.borrow_mut() = Some(PickedFile { name, bytes, path: None });
+            }
+        });
+        reader.set_onload(Some(onload.unchecked_ref()));
+        let _ = reader.read_as_array_buffer(&file);
+    });
+    input.set_onchange(Some(onchange.unchecked_ref()));
+    input.click();
+}
@@ -0,0 +1,411 @@
+use egui::{Color32, Stroke};
+use vek::{Mat4, Vec2};
+
+use super::{linalg, overlay::Overlay};
+
+#[derive(PartialEq, Clone, Copy)]
+enum FitKind {
+    Rigid,
+    Similarity,
+    Affine,
+    Projective,
+}
+
+impl FitKind {
+    fn label(&self) -> &'static str {
+        match self {
+            FitKind::Rigid => "Rigid",
+            FitKind::Similarity => "Similarity",
+            FitKind::Affine => "Affine",
+            FitKind::Projective => "Projective",
+        }
+    }
+
+    fn min_pairs(&self) -> usize {
+        match self {
+            FitKind::Rigid | FitKind::Similarity => 2,
+            FitKind::Affine => 3,
+            FitKind::Projective => 4,
+        }
+    }
+}
+
+type PointPair = (Vec2<f32>, Vec2<f32>); // before, after
+
+pub struct Fit {
+    pub matrix: Mat4<f32>,
+    residuals: Vec<f32>,
+}
+
+// finds the transform of a given kind that best maps "before" points onto "after" points
+pub struct TransformFit {
+    pub enabled: bool,
+    pub placing: bool, // clicks on the canvas place points
+    pairs: Vec<PointPair>,
+    pending: Option<Vec2<f32>>, // a placed "before" point waiting for its "after" point
+    kind: FitKind,
+    csv: String,
+    csv_error: Option<String>,
+}
+
+const BEFORE_COLOR: Color32 = Color32::from_rgb(80, 200, 255);
+const AFTER_COLOR: Color32 = Color32::from_rgb(255, 120, 60);
+const RESIDUAL_COLOR: Color32 = Color32::from_rgb(255, 60, 60);
+
+fn centroid(points: impl Iterator<Item = Vec2<f64>>) -> Vec2<f64> {
+    let (sum, n) = points.fold((Vec2::zero(), 0.0), |(sum, n), p| (sum + p, n + 1.0));
+    sum / n
+}
+
+// closed form 2d procrustes: rotation angle and scale from the centered cross-covariance
+fn fit_similarity(pairs: &[(Vec2<f64>, Vec2<f64>)], allow_scale: bool) -> Option<Mat4<f32>> {
+    let p_mean = centroid(pairs.iter().map(|(p, _)| *p));
+    let q_mean = centroid(pairs.iter().map(|(_, q)| *q));
+    let (mut dot, mut cross, mut norm) = (0.0, 0.0, 0.0);
+    for (p, q) in pairs {
+        let (p, q) = (*p - p_mean, *q - q_mean);
+        dot += p.dot(q);
+        cross += p.x * q.y - p.y * q.x;
+        norm += p.magnitude_squared();
+    }
+    if norm <= f64::EPSILON {
+        return None;
+    }
+    let angle = cross.atan2(dot);
+    let scale = if allow_scale { dot.hypot(cross) / norm } else { 1.0 };
+    let (sin, cos) = angle.sin_cos();
+    let (a, b, c, d) = (scale * cos, -scale * sin, scale * sin, scale * cos);
+    let t = q_mean - Vec2::new(a * p_mean.x + b * p_mean.y, c * p_mean.x + d * p_mean.y);
+    Some(linalg::embed_homography([[a, b, t.x], [c, d, t.y], [0.0, 0.0, 1.0]]))
+}
+
+// least squares through the normal equations, one row of the affine map at a time
+fn fit_affine(pairs: &[(Vec2<f64>, Vec2<f64>)]) -> Option<Mat4<f32>> {
+    let mut ata = vec![vec![0.0; 3]; 3];
+    let mut atb = [vec![0.0; 3], vec![0.0; 3]];
+    for (p, q) in pairs {
+        let row = [p.x, p.y, 1.0];
+        for i in 0..3 {
+            for j in 0..3 {
+                ata[i][j] += row[i] * row[j];
+            }
+            atb[0][i] += row[i] * q.x;
+            atb[1][i] += row[i] * q.y;
+        }
+    }
+    let [x_row, y_row] = atb;
+    let x = linalg::solve(ata.clone(), x_row)?;
+    let y = linalg::solve(ata, y_row)?;
+    Some(linalg::embed_homography([[x[0], x[1], x[2]], [y[0], y[1], y[2]], [0.0, 0.0, 1.0]]))
+}
+
+// moves the centroid to the origin and scales the mean distance to sqrt(2), which keeps the
+// projective normal equations well conditioned
+fn normalizing_transform(points: impl Iterator<Item = Vec2<f64>> + Clone) -> [[f64; 3]; 3] {
+    let mean = centroid(points.clone());
+    let (sum, n) = points.fold((0.0, 0.0), |(sum, n), p| (sum + (p - mean).magnitude(), n + 1.0));
+    let scale = if sum > 0.0 { std::f64::consts::SQRT_2 * n / sum } else { 1.0 };
+    [[scale, 0.0, -scale * mean.x], [0.0, scale, -scale * mean.y], [0.0, 0.0, 1.0]]
+}
+
+fn apply(h: &[[f64; 3]; 3], p: Vec2<f64>) -> Vec2<f64> {
+    let w = h[2][0] * p.x + h[2][1] * p.y + h[2][2];
+    Vec2::new(h[0][0] * p.x + h[0][1] * p.y + h[0][2], h[1][0] * p.x + h[1][1] * p.y + h[1][2]) / w
+}
+
+fn mul3(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut m = [[0.0; 3]; 3];
+    for r in 0..3 {
+        for c in 0..3 {
+            m[r][c] = (0..3).map(|k| a[r][k] * b[k][c]).sum();
+        }
+    }
+    m
+}
+
+// least squares direct linear transform with h22 = 1 on normalized points
+fn fit_projective(pairs: &[(Vec2<f64>, Vec2<f64>)]) -> Option<Mat4<f32>> {
+    let from = normalizing_transform(pairs.iter().map(|(p, _)| *p));
+    let to = normalizing_transform(pairs.iter().map(|(_, q)| *q));
+    let mut ata = vec![vec![0.0; 8]; 8];
+    let mut atb = vec![0.0; 8];
+    for (p, q) in pairs {
+        let (p, q) = (apply(&from, *p), apply(&to, *q));
+        let rows = [
+            ([p.x, p.y, 1.0, 0.0, 0.0, 0.0, -p.x * q.x, -p.y * q.x], q.x),
+            ([0.0, 0.0, 0.0, p.x, p.y, 1.0, -p.x * q.y, -p.y * q.y], q.y),
+        ];
+        for (row, rhs) in rows {
+            for i in 0..8 {
+                for j in 0..8 {
+                    ata[i][j] += row[i] * row[j];
+                }
+                atb[i] += row[i] * rhs;
+            }
+        }
+    }
+    let h = linalg::solve(ata, atb)?;
+    let normalized = [[h[0], h[1], h[2]], [h[3], h[4], h[5]], [h[6], h[7], 1.0]];
+    // undo the normalization: H = T_to^-1 * H' * T_from
+    let to_inverse = [[1.0 / to[0][0], 0.0, -to[0][2] / to[0][0]], [0.0, 1.0 / to[1][1], -to[1][2] / to[1][1]], [0.0, 0.0, 1.0]];
+    let mut h = mul3(&to_inverse, &mul3(&normalized, &from));
+    let mean = centroid(pairs.iter().map(|(p, _)| *p));
+    if h[2][0] * mean.x + h[2][1] * mean.y + h[2][2] < 0.0 {
+        h.iter_mut().flatten().for_each(|x| *x = -*x);
+    }
+    Some(linalg::embed_homography(h))
+}
+
+// parse lines of "x, y, x', y'", skipping a header line if there is one
+fn parse_csv(text: &str) -> Result<Vec<PointPair>, String> {
+    let mut pairs = Vec::new();
+    let mut first = true;
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let header_allowed = std::mem::replace(&mut first, false);
+        let values = line
+            .split([',', ';', '\t'])
+            .map(|v| v.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>();
+        match values {
+            Ok(v) if v.len() >= 4 => pairs.push((Vec2::new(v[0], v[1]), Vec2::new(v[2], v[3]))),
+            Ok(_) => return Err(format!("Line {} needs four values: x, y, x', y'", n + 1)),
+            Err(_) if header_allowed => continue, // header
+            Err(e) => return Err(format!("Line {}: {}", n + 1, e)),
+        }
+    }
+    Ok(pairs)
+}
+
+impl TransformFit {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            placing: false,
+            pairs: Vec::new(),
+            pending: None,
+            kind: FitKind::Affine,
+            csv: String::new(),
+            csv_error: None,
+        }
+    }
+
+    // clicks alternate between placing a "before" point and its "after" point
+    pub fn place(&mut self, world: Vec2<f32>) {
+        match self.pending.take() {
+            Some(before) => self.pairs.push((before, world)),
+            None => self.pending = Some(world),
+        }
+    }
+
+    pub fn fit(&self) -> Option<Fit> {
+        if self.pairs.len() < self.kind.min_pairs() {
+            return None;
+        }
+        let pairs = self.pairs.iter().map(|(p, q)| (p.as_::<f64>(), q.as_::<f64>())).collect::<Vec<_>>();
+        let matrix = match self.kind {
+            FitKind::Rigid => fit_similarity(&pairs, false),
+            FitKind::Similarity => fit_similarity(&pairs, true),
+            FitKind::Affine => fit_affine(&pairs),
+            FitKind::Projective => fit_projective(&pairs),
+        }?;
+        if !linalg::is_finite(&matrix) {
+            return None;
+        }
+        let residuals = self
+            .pairs
+            .iter()
+            .map(|(p, q)| linalg::transform_point_2d(&matrix, *p).distance(*q))
+            .collect();
+        Some(Fit { matrix, residuals })
+    }
+
+    // returns a fitted matrix when the user asks for it to be added to the stack
+    pub fn gui(&mut self, ui: &mut egui::Ui) -> Option<Mat4<f32>> {
+        ui.horizontal(|ui| {
+            for kind in [FitKind::Rigid, FitKind::Similarity, FitKind::Affine, FitKind::Projective] {
+                ui.selectable_value(&mut self.kind, kind, kind.label());
+            }
+        });
+        if ui.checkbox(&mut self.placing, "Place points on the canvas").changed() {
+            self.pending = None;
+        }
+        if self.placing {
+            ui.label(if self.pending.is_some() { "Click where the point ends up." } else { "Click a point before the transform." });
+        }
+        ui.collapsing("Import CSV (x, y, x', y' per line)", |ui| {
+            ui.add(egui::TextEdit::multiline(&mut self.csv).desired_rows(4).code_editor());
+            if ui.button("Import").clicked() {
+                match parse_csv(&self.csv) {
+                    Ok(pairs) => {
+                        self.pairs.extend(pairs);
+                        self.csv_error = None;
+                    }
+                    Err(e) => self.csv_error = Some(e),
+                }
+            }
+            if let Some(e) = &self.csv_error {
+                ui.colored_label(ui.visuals().warn_fg_color, e);
+            }
+        });
+
+        let fit = self.fit();
+        let mut remove = None;
+        egui::Grid::new("fit_pairs").striped(true).show(ui, |ui| {
+            ui.label("#");
+            ui.colored_label(BEFORE_COLOR, "Before");
+            ui.colored_label(AFTER_COLOR, "After");
+            ui.label("Residual");
+            ui.end_row();
+            for (i, (p, q)) in self.pairs.iter_mut().enumerate() {
+                ui.label(format!("{}", i + 1));
+                for v in [p, q] {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut v.x).speed(0.01));
+                        ui.add(egui::DragValue::new(&mut v.y).speed(0.01));
+                    });
+                }
+                match &fit {
+                    Some(fit) => ui.label(format!("{:.4}", fit.residuals[i])),
+                    None => ui.label("-"),
+                };
+                if ui.small_button("🗑").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove {
+            self.pairs.remove(i);
+        }
+        if ui.button("Clear points").clicked() {
+            self.pairs.clear();
+            self.pending = None;
+        }
+        ui.separator();
+        match fit {
+            Some(fit) => {
+                let rms = (fit.residuals.iter().map(|r| r * r).sum::<f32>() / fit.residuals.len() as f32).sqrt();
+                ui.label(format!("RMS error: {rms:.4}"));
+                if ui.button("Add to transform stack").clicked() {
+                    return Some(fit.matrix);
+                }
+            }
+            None if self.pairs.len() < self.kind.min_pairs() => {
+                ui.label(format!("A {} fit needs at least {} point pairs.", self.kind.label().to_lowercase(), self.kind.min_pairs()));
+            }
+            None => {
+                ui.colored_label(ui.visuals().warn_fg_color, "The points are degenerate for this kind of transform.");
+            }
+        }
+        None
+    }
+
+    pub fn draw(&self, overlay: &Overlay) {
+        let painter = &overlay.painter;
+        let fit = self.fit();
+        for (i, (p, q)) in self.pairs.iter().enumerate() {
+            let (before, after) = (overlay.to_screen(*p), overlay.to_screen(*q));
+            painter.arrow(before, after - before, Stroke::new(1.0, Color32::GRAY));
+            if let Some(fit) = &fit {
+                let fitted = overlay.to_screen(linalg::transform_point_2d(&fit.matrix, *p));
+                painter.line_segment([fitted, after], Stroke::new(2.0, RESIDUAL_COLOR));
+            }
+            painter.circle_stroke(before, 4.0, Stroke::new(2.0, BEFORE_COLOR));
+            painter.circle_filled(after, 4.0, AFTER_COLOR);
+            painter.text(after + egui::vec2(5.0, -5.0), egui::Align2::LEFT_BOTTOM, (i + 1).to_string(), egui::FontId::proportional(12.0), AFTER_COLOR);
+        }
+        if let Some(pending) = self.pending {
+            painter.circle_stroke(overlay.to_screen(pending), 4.0, Stroke::new(2.0, BEFORE_COLOR));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vek::Vec3;
+
+    use super::*;
+
+    const BEFORE: [Vec2<f32>; 5] = [
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(-0.5, 2.0),
+        Vec2::new(0.3, -1.2),
+    ];
+
+    fn fitter(kind: FitKind, pairs: Vec<PointPair>) -> TransformFit {
+        TransformFit { kind, pairs, ..TransformFit::new() }
+    }
+
+    // fits the before points mapped through the matrix and checks the fit maps the plane the same
+    // way, homographies only being defined up to scale
+    fn assert_recovers(kind: FitKind, matrix: Mat4<f32>) {
+        let pairs = BEFORE.iter().map(|p| (*p, linalg::transform_point_2d(&matrix, *p))).collect();
+        let fit = fitter(kind, pairs).fit().unwrap();
+        assert!(fit.residuals.iter().all(|r| *r <= 1e-4), "{} fit has residuals {:?}", kind.label(), fit.residuals);
+        for p in [Vec2::new(2.0, -1.0), Vec2::new(-1.5, -0.5), Vec2::new(0.5, 0.5)] {
+            let (fitted, expected) = (linalg::transform_point_2d(&fit.matrix, p), linalg::transform_point_2d(&matrix, p));
+            assert!(fitted.distance(expected) <= 1e-4, "{} fit maps {p:?} to {fitted:?} instead of {expected:?}", kind.label());
+        }
+    }
+
+    #[test]
+    fn recovers_known_transforms() {
+        let rigid = Mat4::<f32>::translation_2d(Vec2::new(1.5, -0.5)) * Mat4::rotation_z(0.8);
+        assert_recovers(FitKind::Rigid, rigid);
+        assert_recovers(FitKind::Similarity, rigid * Mat4::scaling_3d(Vec3::new(2.5, 2.5, 1.0)));
+        let affine = Mat4::from_row_arrays([
+            [1.2, 0.4, 0.0, -0.3],
+            [-0.2, 0.7, 0.0, 0.9],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        assert_recovers(FitKind::Affine, affine);
+        let projective = Mat4::from_row_arrays([
+            [1.1, 0.2, 0.0, 0.5],
+            [-0.3, 0.9, 0.0, -0.2],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.15, -0.1, 0.0, 1.0],
+        ]);
+        assert_recovers(FitKind::Projective, projective);
+    }
+
+    #[test]
+    fn degenerate_points_have_no_fit() {
+        let coincident = vec![(Vec2::new(1.0, 1.0), Vec2::new(0.0, 0.0)); 4];
+        let collinear: Vec<PointPair> = (0..4).map(|i| (Vec2::new(i as f32, 2.0 * i as f32), Vec2::new(i as f32, 0.0))).collect();
+        for kind in [FitKind::Rigid, FitKind::Similarity, FitKind::Affine, FitKind::Projective] {
+            assert!(fitter(kind, coincident.clone()).fit().is_none(), "{} fit of coincident points", kind.label());
+        }
+        for kind in [FitKind::Affine, FitKind::Projective] {
+            assert!(fitter(kind, collinear.clone()).fit().is_none(), "{} fit of collinear points", kind.label());
+        }
+        // too few pairs
+        assert!(fitter(FitKind::Projective, collinear[..3].to_vec()).fit().is_none());
+    }
+
+    #[test]
+    fn csv_with_header() {
+        let pairs = parse_csv("\n x, y, x', y'\n0, 0, 1, 1\n\n2; 3; 4; 5\n-1\t0.5\t2\t-3\n").unwrap();
+        assert_eq!(
+            pairs,
+            [
+                (Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0)),
+                (Vec2::new(2.0, 3.0), Vec2::new(4.0, 5.0)),
+                (Vec2::new(-1.0, 0.5), Vec2::new(2.0, -3.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_errors_name_the_line() {
+        assert_eq!(parse_csv("x, y, x', y'\n0, 0, 1, 1\n2, 3, 4").unwrap_err(), "Line 3 needs four values: x, y, x', y'");
+        // only the first line can be a header
+        assert!(parse_csv("0, 0, 1, 1\nx, y, x', y'").unwrap_err().starts_with("Line 2: "));
+    }
+}
@@ -0,0 +1,249 @@
+use egui::{Color32, Stroke};
+use vek::{Mat4, Vec2};
+
+use super::{
+    camera::Camera,
+    linalg,
+    overlay::Overlay,
+    stack::{MatrixInteractionType, StackEntry},
+};
+
+pub const HANDLE_RADIUS: f32 = 6.0; // in points
+const RING_RADIUS: f32 = 60.0; // in points
+
+pub const SOURCE_COLOR: Color32 = Color32::from_rgb(80, 200, 255);
+pub const TARGET_COLOR: Color32 = Color32::from_rgb(255, 120, 60);
+// same colours as the axes of the grid
+const X_COLOR: Color32 = Color32::from_rgb(255, 60, 60);
+const Y_COLOR: Color32 = Color32::from_rgb(60, 255, 60);
+const HANDLE_COLOR: Color32 = Color32::from_rgb(255, 220, 60);
+
+#[derive(Clone, Copy, PartialEq)]
+enum Handle {
+    ControlPoint(usize), // homography source points 0..4, target points 4..8
+    Translation,
+    Rotation,
+    ScaleX,
+    ScaleY,
+    Scale,
+    Origin, // translation column of a custom matrix
+    BasisX, // tip of the transformed x basis vector
+    BasisY,
+}
+
+struct Drag {
+    entry: usize,
+    handle: Handle,
+    // handle position minus cursor position when grabbed, so handles don't jump to the cursor.
+    // for the rotation ring it is (angle minus cursor angle, 0)
+    offset: Vec2<f32>,
+}
+
+// position of each handle of an entry, in the space the entry maps into
+fn point_handles(interaction_type: &MatrixInteractionType, mat: &Mat4<f32>) -> Vec<(Handle, Vec2<f32>)> {
+    match interaction_type {
+        MatrixInteractionType::Homography { source, target } => source
+            .iter()
+            .chain(target.iter())
+            .enumerate()
+            .map(|(n, p)| (Handle::ControlPoint(n), *p))
+            .collect(),
+        MatrixInteractionType::TranslationMatrix2D(offset) => vec![(Handle::Translation, *offset)],
+        MatrixInteractionType::ScaleMatrix2D(scale) => vec![
+            (Handle::ScaleX, Vec2::new(scale.x, 0.0)),
+            (Handle::ScaleY, Vec2::new(0.0, scale.y)),
+            (Handle::Scale, *scale),
+        ],
+        MatrixInteractionType::CustomMatrix => {
+            let origin = Vec2::new(mat[(0, 3)], mat[(1, 3)]);
+            vec![
+                (Handle::Origin, origin),
+                (Handle::BasisX, origin + Vec2::new(mat[(0, 0)], mat[(1, 0)])),
+                (Handle::BasisY, origin + Vec2::new(mat[(0, 1)], mat[(1, 1)])),
+            ]
+        }
+        _ => vec![],
+    }
+}
+
+fn angle_of(v: Vec2<f32>) -> f32 {
+    v.y.atan2(v.x)
+}
+
+// on-canvas handles for editing the selected stack entry
+pub struct Gizmo {
+    pub visible: bool,
+    drag: Option<Drag>,
+}
+
+impl Gizmo {
+    pub fn new() -> Self {
+        Self {
+            visible: true,
+            drag: None,
+        }
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    pub fn release(&mut self) {
+        self.drag = None;
+    }
+
+    pub fn dragged_entry(&self) -> Option<usize> {
+        self.drag.as_ref().map(|drag| drag.entry)
+    }
+
+    // try to pick up a handle of the selected entry under the cursor, given in physical pixels.
+    // `above` takes the space the entry maps into to the world
+    #[allow(clippy::too_many_arguments)]
+    pub fn grab(&mut self, entries: &[StackEntry], selected: usize, above: Mat4<f32>, mouse: Vec2<f32>, camera: &Camera, window_size: Vec2<f32>, pixels_per_point: f32) {
+        if !self.visible || selected >= entries.len() {
+            return;
+        }
+        let Some(inverse) = linalg::invert(&above).inverse else {
+            return;
+        };
+        let StackEntry { matrix: mat, interaction_type, .. } = &entries[selected];
+        let to_screen = |p: Vec2<f32>| camera.world_to_screen(linalg::transform_point_2d(&above, p), window_size);
+        let local_mouse = linalg::transform_point_2d(&inverse, camera.screen_to_world(mouse, window_size));
+        let radius = HANDLE_RADIUS * pixels_per_point;
+
+        let closest = point_handles(interaction_type, mat)
+            .into_iter()
+            .map(|(handle, p)| (handle, p, to_screen(p).distance(mouse)))
+            .filter(|(_, _, distance)| *distance <= radius)
+            .min_by(|a, b| a.2.total_cmp(&b.2));
+        if let Some((handle, p, _)) = closest {
+            self.drag = Some(Drag { entry: selected, handle, offset: p - local_mouse });
+            return;
+        }
+        if let MatrixInteractionType::RotationMatrixZ(angle) = interaction_type {
+            let ring_distance = to_screen(Vec2::zero()).distance(mouse) - RING_RADIUS * pixels_per_point;
+            if ring_distance.abs() <= radius {
+                self.drag = Some(Drag {
+                    entry: selected,
+                    handle: Handle::Rotation,
+                    offset: Vec2::new(angle - angle_of(local_mouse), 0.0),
+                });
+            }
+        }
+    }
+
+    // move the grabbed handle to the cursor, writing back into the entry
+    pub fn drag(&mut self, entries: &mut [StackEntry], above: Mat4<f32>, mouse: Vec2<f32>, camera: &Camera, window_size: Vec2<f32>) {
+        let Some(drag) = &self.drag else {
+            return;
+        };
+        if drag.entry >= entries.len() {
+            self.drag = None;
+            return;
+        }
+        // bring the cursor back into the space the entry maps into
+        let Some(inverse) = linalg::invert(&above).inverse else {
+            return;
+        };
+        let local_mouse = linalg::transform_point_2d(&inverse, camera.screen_to_world(mouse, window_size));
+        let p = local_mouse + drag.offset;
+        let StackEntry { matrix: mat, interaction_type, .. } = &mut entries[drag.entry];
+        match (interaction_type, drag.handle) {
+            (MatrixInteractionType::Homography { source, .. }, Handle::ControlPoint(n)) if n < 4 => source[n] = p,
+            (MatrixInteractionType::Homography { target, .. }, Handle::ControlPoint(n)) => target[n - 4] = p,
+            (MatrixInteractionType::TranslationMatrix2D(offset), Handle::Translation) => *offset = p,
+            (MatrixInteractionType::RotationMatrixZ(angle), Handle::Rotation) => *angle = drag.offset.x + angle_of(local_mouse),
+            (MatrixInteractionType::ScaleMatrix2D(scale), Handle::ScaleX) => scale.x = p.x,
+            (MatrixInteractionType::ScaleMatrix2D(scale), Handle::ScaleY) => scale.y = p.y,
+            (MatrixInteractionType::ScaleMatrix2D(scale), Handle::Scale) => *scale = p,
+            (MatrixInteractionType::CustomMatrix, Handle::Origin) => {
+                mat[(0, 3)] = p.x;
+                mat[(1, 3)] = p.y;
+            }
+            (MatrixInteractionType::CustomMatrix, Handle::BasisX) => {
+                mat[(0, 0)] = p.x - mat[(0, 3)];
+                mat[(1, 0)] = p.y - mat[(1, 3)];
+            }
+            (MatrixInteractionType::CustomMatrix, Handle::BasisY) => {
+                mat[(0, 1)] = p.x - mat[(0, 3)];
+                mat[(1, 1)] = p.y - mat[(1, 3)];
+            }
+            _ => {}
+        }
+    }
+
+    pub fn draw(&self, overlay: &Overlay, entries: &[StackEntry], selected: usize, above: Mat4<f32>) {
+        if !self.visible || selected >= entries.len() {
+            return;
+        }
+        let painter = &overlay.painter;
+        let to_screen = |p: Vec2<f32>| overlay.to_screen(linalg::transform_point_2d(&above, p));
+        let StackEntry { matrix: mat, interaction_type, .. } = &entries[selected];
+        let handles = point_handles(interaction_type, mat);
+        let position = |handle: Handle| handles.iter().find(|(h, _)| *h == handle).map(|(_, p)| to_screen(*p));
+        let stroke = |color| Stroke::new(2.0, color);
+
+        match interaction_type {
+            MatrixInteractionType::Homography { .. } => {
+                for (quad, color) in handles.chunks(4).zip([SOURCE_COLOR, TARGET_COLOR]) {
+                    let mut outline = quad.iter().map(|(_, p)| to_screen(*p)).collect::<Vec<_>>();
+                    outline.push(outline[0]);
+                    painter.line(outline, Stroke::new(1.0, color));
+                    for (n, (_, p)) in quad.iter().enumerate() {
+                        let pos = to_screen(*p);
+                        if color == SOURCE_COLOR {
+                            painter.circle_stroke(pos, HANDLE_RADIUS, stroke(color));
+                        } else {
+                            painter.circle_filled(pos, HANDLE_RADIUS, color);
+                        }
+                        painter.text(pos + egui::vec2(HANDLE_RADIUS, -HANDLE_RADIUS), egui::Align2::LEFT_BOTTOM, (n + 1).to_string(), egui::FontId::proportional(12.0), color);
+                    }
+                }
+            }
+            MatrixInteractionType::TranslationMatrix2D(_) => {
+                let origin = to_screen(Vec2::zero());
+                if let Some(tip) = position(Handle::Translation) {
+                    painter.arrow(origin, tip - origin, Stroke::new(1.0, HANDLE_COLOR));
+                    painter.circle_filled(tip, HANDLE_RADIUS, HANDLE_COLOR);
+                }
+            }
+            MatrixInteractionType::RotationMatrixZ(angle) => {
+                let center = to_screen(Vec2::zero());
+                painter.circle_stroke(center, RING_RADIUS, stroke(HANDLE_COLOR));
+                // the ring is drawn in screen space, the spoke shows where the x axis ends up
+                let direction = to_screen(Vec2::new(angle.cos(), angle.sin())) - center;
+                if direction.length() > 0.0 {
+                    let spoke = center + direction.normalized() * RING_RADIUS;
+                    painter.line_segment([center, spoke], Stroke::new(1.0, HANDLE_COLOR));
+                    painter.circle_filled(spoke, HANDLE_RADIUS / 2.0, HANDLE_COLOR);
+                }
+            }
+            MatrixInteractionType::ScaleMatrix2D(_) => {
+                let origin = to_screen(Vec2::zero());
+                for (handle, color) in [(Handle::ScaleX, X_COLOR), (Handle::ScaleY, Y_COLOR), (Handle::Scale, HANDLE_COLOR)] {
+                    if let Some(pos) = position(handle) {
+                        painter.line_segment([origin, pos], Stroke::new(1.0, color));
+                        painter.rect_filled(egui::Rect::from_center_size(pos, egui::Vec2::splat(2.0 * HANDLE_RADIUS)), 0.0, color);
+                    }
+                }
+            }
+            MatrixInteractionType::CustomMatrix => {
+                if let (Some(origin), Some(x), Some(y)) = (position(Handle::Origin), position(Handle::BasisX), position(Handle::BasisY)) {
+                    painter.arrow(origin, x - origin, stroke(X_COLOR));
+                    painter.arrow(origin, y - origin, stroke(Y_COLOR));
+                    painter.circle_filled(x, HANDLE_RADIUS, X_COLOR);
+                    painter.circle_filled(y, HANDLE_RADIUS, Y_COLOR);
+                    painter.circle_filled(origin, HANDLE_RADIUS, HANDLE_COLOR);
+                }
+            }
+            _ => {}
+        }
+        // outline the handle being dragged
+        if let Some(drag) = &self.drag
+            && drag.entry == selected
+            && let Some(pos) = position(drag.handle)
+        {
+            painter.circle_stroke(pos, HANDLE_RADIUS + 3.0, Stroke::new(1.0, Color32::WHITE));
+        }
+    }
+}
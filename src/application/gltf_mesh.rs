@@ -0,0 +1,161 @@
+use gltf::image::Format;
+use image::{DynamicImage, ImageBuffer};
+use vek::{Mat4, Vec2, Vec3, Vec4};
+
+use super::{
+    mesh::{self, MeshVertex},
+    texture::{self, Image},
+};
+use crate::renderer::Vertex;
+
+// the triangles of a node and its children, in the coordinates of the scene
+fn add_node(node: &gltf::Node, parent: Mat4<f32>, buffers: &[gltf::buffer::Data], vertices: &mut Vec<MeshVertex>, indices: &mut Vec<u32>) -> Result<(), String> {
+    let transform = parent * Mat4::from_col_arrays(node.transform().matrix());
+    let normal_transform = transform.inverted().transposed();
+    for primitive in node.mesh().iter().flat_map(|mesh| mesh.primitives()) {
+        if primitive.mode() != gltf::mesh::Mode::Triangles {
+            continue;
+        }
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data[..]));
+        let Some(positions) = reader.read_positions() else {
+            continue;
+        };
+        let positions = positions.collect::<Vec<_>>();
+        let normals = reader.read_normals().map(|normals| normals.collect::<Vec<_>>());
+        let uvs = reader.read_tex_coords(0).map(|uvs| uvs.into_f32().collect::<Vec<_>>());
+        let colors = reader.read_colors(0).map(|colors| colors.into_rgba_f32().collect::<Vec<_>>());
+        // every attribute needs a value for every vertex
+        let count = positions.len();
+        if normals.as_ref().is_some_and(|v| v.len() != count) || uvs.as_ref().is_some_and(|v| v.len() != count) || colors.as_ref().is_some_and(|v| v.len() != count) {
+            return Err("The attributes of a primitive have different lengths".into());
+        }
+        // the default material is plain white, which would hide the corner colours
+        let material = primitive.material();
+        let factor = material.index().map(|_| Vec4::from(material.pbr_metallic_roughness().base_color_factor()));
+        let first = vertices.len() as u32;
+        for (i, position) in positions.into_iter().enumerate() {
+            let color = match (colors.as_ref().map(|colors| Vec4::from(colors[i])), factor) {
+                (Some(color), Some(factor)) => Some(color * factor),
+                (color, factor) => color.or(factor),
+            };
+            vertices.push(MeshVertex {
+                position: (transform * Vec4::from_point(Vec3::from(position))).xyz(),
+                normal: normals.as_ref().map(|normals| (normal_transform * Vec4::from_direction(Vec3::from(normals[i]))).xyz()),
+                // gltf puts v = 0 at the top of the image, the model has it at the bottom
+                uv: uvs.as_ref().map(|uvs| Vec2::new(uvs[i][0], 1.0 - uvs[i][1])),
+                color,
+            });
+        }
+        match reader.read_indices() {
+            Some(read) => {
+                let read = read.into_u32().collect::<Vec<_>>();
+                if read.iter().any(|i| *i as usize >= count) || !read.len().is_multiple_of(3) {
+                    return Err("A primitive has indices that don't make up triangles of its vertices".into());
+                }
+                indices.extend(read.into_iter().map(|i| first + i));
+            }
+            None if !count.is_multiple_of(3) => return Err("A primitive has vertices that don't make up triangles".into()),
+            None => indices.extend(first..vertices.len() as u32),
+        }
+    }
+    for child in node.children() {
+        add_node(&child, transform, buffers, vertices, indices)?;
+    }
+    Ok(())
+}
+
+// the vertices and indices of the scene, with the base colour texture if there is one
+pub type GltfMesh = (Vec<Vertex>, Vec<u32>, Option<Image>);
+
+// the base colour texture of the first material that has one
+fn base_color_texture(document: &gltf::Document, images: &[gltf::image::Data]) -> Option<Image> {
+    let info = document.materials().find_map(|material| material.pbr_metallic_roughness().base_color_texture())?;
+    let data = images.get(info.texture().source().index())?;
+    let (width, height, pixels) = (data.width, data.height, data.pixels.clone());
+    let image = match data.format {
+        Format::R8 => DynamicImage::ImageLuma8(ImageBuffer::from_raw(width, height, pixels)?),
+        Format::R8G8 => DynamicImage::ImageLumaA8(ImageBuffer::from_raw(width, height, pixels)?),
+        Format::R8G8B8 => DynamicImage::ImageRgb8(ImageBuffer::from_raw(width, height, pixels)?),
+        Format::R8G8B8A8 => DynamicImage::ImageRgba8(ImageBuffer::from_raw(width, height, pixels)?),
+        _ => return None,
+    };
+    Some(texture::fit(image))
+}
+
+// gltf 2.0, either a .glb or a .gltf with embedded buffers. natively, a path lets buffers
+// next to the file be found as well
+pub fn load(bytes: &[u8], path: Option<&str>) -> Result<GltfMesh, String> {
+    let imported = match path {
+        #[cfg(not(target_arch = "wasm32"))]
+        Some(path) => gltf::import(path),
+        _ => gltf::import_slice(bytes),
+    };
+    let (document, buffers, images) = imported.map_err(|e| format!("Couldn't load the glTF file: {e}"))?;
+    let scene = document.default_scene().or_else(|| document.scenes().next()).ok_or("The file contains no scene")?;
+    let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+    for node in scene.nodes() {
+        add_node(&node, Mat4::identity(), &buffers, &mut vertices, &mut indices)?;
+    }
+    if indices.is_empty() {
+        return Err("The scene contains no triangles".into());
+    }
+    Ok((mesh::fit_mesh(&vertices), indices, base_color_texture(&document, &images)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a .glb with one triangle of three positions and the given indices, as u16
+    fn glb(indices: &[u16]) -> Vec<u8> {
+        let mut bin: Vec<u8> = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].iter().flat_map(|f| f.to_le_bytes()).collect();
+        bin.extend(indices.iter().flat_map(|i| i.to_le_bytes()));
+        while !bin.len().is_multiple_of(4) {
+            bin.push(0);
+        }
+        let json = format!(
+            r#"{{"asset":{{"version":"2.0"}},"scene":0,"scenes":[{{"nodes":[0]}}],"nodes":[{{"mesh":0}}],
+            "meshes":[{{"primitives":[{{"attributes":{{"POSITION":0}},"indices":1}}]}}],
+            "buffers":[{{"byteLength":{}}}],
+            "bufferViews":[{{"buffer":0,"byteOffset":0,"byteLength":36}},{{"buffer":0,"byteOffset":36,"byteLength":{}}}],
+            "accessors":[{{"bufferView":0,"componentType":5126,"count":3,"type":"VEC3","min":[0,0,0],"max":[1,1,0]}},
+            {{"bufferView":1,"componentType":5123,"count":{},"type":"SCALAR"}}]}}"#,
+            bin.len(),
+            indices.len() * 2,
+            indices.len()
+        );
+        let mut json = json.into_bytes();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+        let mut file = Vec::new();
+        file.extend(b"glTF");
+        file.extend(2u32.to_le_bytes());
+        file.extend(((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+        file.extend((json.len() as u32).to_le_bytes());
+        file.extend(b"JSON");
+        file.extend(json);
+        file.extend((bin.len() as u32).to_le_bytes());
+        file.extend(b"BIN\0");
+        file.extend(bin);
+        file
+    }
+
+    #[test]
+    fn loads_triangle() {
+        let (vertices, indices, texture) = load(&glb(&[0, 1, 2]), None).unwrap();
+        assert_eq!(vertices.len(), 3);
+        assert_eq!(indices, [0, 1, 2]);
+        assert!(texture.is_none());
+    }
+
+    #[test]
+    fn rejects_indices_past_vertices() {
+        assert!(load(&glb(&[0, 1, 5]), None).is_err());
+    }
+
+    #[test]
+    fn rejects_partial_triangles() {
+        assert!(load(&glb(&[0, 1, 2, 0]), None).is_err());
+    }
+}
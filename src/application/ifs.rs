@@ -0,0 +1,149 @@
+use bytemuck::{Pod, Zeroable};
+use vek::{Mat4, Vec2, Vec3};
+
+pub const MAX_MAPS: usize = 16;
+
+// mirrors the Ifs uniform block in ifs.vert
+#[repr(C)]
+#[derive(Copy, Clone, Zeroable, Pod)]
+pub struct IfsData {
+    info: [u32; 4], // map count, iterations, unused, unused
+    cumulative: [f32; MAX_MAPS],
+    maps: [Mat4<f32>; MAX_MAPS],
+}
+
+// renders the attractor of the iterated function system made up of the enabled stack entries,
+// each weighted by its own ifs_weight
+pub struct IteratedFunctionSystem {
+    pub enabled: bool,
+    pub point_count: u32,
+    iterations: u32,
+}
+
+pub struct Preset {
+    pub name: &'static str,
+    pub maps: Vec<(Mat4<f32>, f32)>, // map and its weight
+}
+
+// affine map x -> Ax + b, with A given row by row
+fn affine(a: f32, b: f32, c: f32, d: f32, translation: Vec2<f32>) -> Mat4<f32> {
+    Mat4::new(
+        a, b, 0.0, translation.x,
+        c, d, 0.0, translation.y,
+        0.0, 0.0, 1.0, 0.0,
+        0.0, 0.0, 0.0, 1.0,
+    )
+}
+
+// x -> scale * R(angle) x + translation
+fn similarity(scale: f32, angle: f32, translation: Vec2<f32>) -> Mat4<f32> {
+    let rotation: Mat4<f32> = Mat4::rotation_z(angle);
+    let scaling: Mat4<f32> = Mat4::scaling_3d(Vec3::new(scale, scale, 1.0));
+    Mat4::<f32>::translation_2d(translation) * rotation * scaling
+}
+
+// express a map in coordinates where its attractor fits the default view
+fn fit_to_view(map: Mat4<f32>, scale: f32, offset: Vec2<f32>) -> Mat4<f32> {
+    let scaling: Mat4<f32> = Mat4::scaling_3d(Vec3::new(scale, scale, 1.0));
+    let to_view = Mat4::<f32>::translation_2d(offset) * scaling;
+    to_view * map * to_view.inverted()
+}
+
+pub fn presets() -> Vec<Preset> {
+    let third = 1.0 / 3.0;
+    let sixth = 1.0 / 6.0;
+    let root3 = 3f32.sqrt();
+    let angle = std::f32::consts::FRAC_PI_3;
+    vec![
+        Preset {
+            name: "Sierpinski triangle",
+            maps: [Vec2::new(-0.5, -0.5), Vec2::new(0.5, -0.5), Vec2::new(0.0, 0.5)]
+                .into_iter()
+                .map(|t| (similarity(0.5, 0.0, t), 1.0))
+                .collect(),
+        },
+        Preset {
+            name: "Sierpinski carpet",
+            maps: (0..9)
+                .filter(|i| *i != 4)
+                .map(|i| {
+                    let t = Vec2::new((i % 3) as f32 - 1.0, (i / 3) as f32 - 1.0) * 2.0 * third;
+                    (similarity(third, 0.0, t), 1.0)
+                })
+                .collect(),
+        },
+        Preset {
+            // the classic coefficients, where the fern spans roughly [-2.2, 2.7] x [0, 10]
+            name: "Barnsley fern",
+            maps: vec![
+                (affine(0.0, 0.0, 0.0, 0.16, Vec2::zero()), 0.01),
+                (affine(0.85, 0.04, -0.04, 0.85, Vec2::new(0.0, 1.6)), 0.85),
+                (affine(0.2, -0.26, 0.23, 0.22, Vec2::new(0.0, 1.6)), 0.07),
+                (affine(-0.15, 0.28, 0.26, 0.24, Vec2::new(0.0, 0.44)), 0.07),
+            ]
+            .into_iter()
+            .map(|(m, p)| (fit_to_view(m, 0.2, Vec2::new(0.0, -1.0)), p))
+            .collect(),
+        },
+        Preset {
+            name: "Heighway dragon",
+            maps: vec![
+                (affine(0.5, -0.5, 0.5, 0.5, Vec2::zero()), 1.0),
+                (affine(-0.5, -0.5, 0.5, -0.5, Vec2::new(1.0, 0.0)), 1.0),
+            ]
+            .into_iter()
+            .map(|(m, p)| (fit_to_view(m, 1.5, Vec2::new(-0.75, -0.25)), p))
+            .collect(),
+        },
+        Preset {
+            // four copies of the curve between (-1, 0) and (1, 0), each a third of the size
+            name: "Koch curve",
+            maps: vec![
+                (similarity(third, 0.0, Vec2::new(-2.0 * third, 0.0)), 1.0),
+                (similarity(third, angle, Vec2::new(-sixth, root3 * sixth)), 1.0),
+                (similarity(third, -angle, Vec2::new(sixth, root3 * sixth)), 1.0),
+                (similarity(third, 0.0, Vec2::new(2.0 * third, 0.0)), 1.0),
+            ],
+        },
+    ]
+}
+
+impl IteratedFunctionSystem {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            point_count: 200_000,
+            iterations: 32,
+        }
+    }
+
+    // the maps with their weights
+    pub fn data(&self, maps: impl Iterator<Item = (Mat4<f32>, f32)>) -> IfsData {
+        let mut data = IfsData::zeroed();
+        let mut total = 0.0;
+        let mut count = 0;
+        for (map, weight) in maps.take(MAX_MAPS) {
+            total += weight.max(0.0);
+            data.cumulative[count] = total;
+            data.maps[count] = map;
+            count += 1;
+        }
+        if total > 0.0 {
+            for c in data.cumulative.iter_mut() {
+                *c /= total;
+            }
+        }
+        data.info = [count as u32, self.iterations, 0, 0];
+        data
+    }
+
+    pub fn gui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Each active stack entry is one map of the IFS.");
+        ui.add(
+            egui::Slider::new(&mut self.point_count, 1_000..=2_000_000)
+                .logarithmic(true)
+                .text("Points"),
+        );
+        ui.add(egui::Slider::new(&mut self.iterations, 1..=100).text("Iterations"));
+    }
+}
@@ -0,0 +1,90 @@
+use super::{
+    files::{FilePicker, PickedFile},
+    gltf_mesh, obj,
+    shapes::Shape,
+    svg,
+    texture::Image,
+};
+
+// loads shapes for the model from files
+pub struct ModelImport {
+    pub enabled: bool,
+    picker: FilePicker,
+    tolerance: f32, // for flattening svg curves, as a fraction of the drawing's size
+    file: Option<PickedFile>, // kept around to flatten again with another tolerance
+    error: Option<String>,
+    pub shape: Option<Shape>, // the last shape that loaded
+    texture: Option<(String, Image)>, // the texture that came with it, until it is taken
+}
+
+impl ModelImport {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            picker: FilePicker::new(&["svg", "obj", "gltf", "glb"]),
+            tolerance: 0.002,
+            file: None,
+            error: None,
+            shape: None,
+            texture: None,
+        }
+    }
+
+    fn parse(&self) -> Option<Result<(Shape, Option<Image>), String>> {
+        let file = self.file.as_ref()?;
+        let name = file.name.clone();
+        let extension = name.rsplit_once('.').map(|(_, extension)| extension.to_lowercase()).unwrap_or_default();
+        let mesh = |vertices, indices| Shape::Mesh { name: name.clone(), vertices, indices };
+        Some(match extension.as_str() {
+            "svg" => svg::parse(&String::from_utf8_lossy(&file.bytes), self.tolerance).map(|pieces| (Shape::Svg { name: name.clone(), pieces }, None)),
+            "obj" => obj::parse(&String::from_utf8_lossy(&file.bytes)).map(|(vertices, indices)| (mesh(vertices, indices), None)),
+            "gltf" | "glb" => gltf_mesh::load(&file.bytes, file.path.as_deref()).map(|(vertices, indices, texture)| (mesh(vertices, indices), texture)),
+            _ => Err(format!("Unsupported file type \"{name}\"")),
+        })
+    }
+
+    // returns a shape when a file was loaded and should replace the model
+    pub fn load(&mut self, file: Result<PickedFile, String>) -> Option<Shape> {
+        self.file = None;
+        self.error = None;
+        match file {
+            Ok(file) => self.file = Some(file),
+            Err(e) => self.error = Some(e),
+        }
+        self.reparse()
+    }
+
+    fn reparse(&mut self) -> Option<Shape> {
+        match self.parse()? {
+            Ok((shape, texture)) => {
+                self.shape = Some(shape.clone());
+                self.texture = texture.zip(self.file.as_ref()).map(|(image, file)| (file.name.clone(), image));
+                Some(shape)
+            }
+            Err(e) => {
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+
+    // the texture of the model that loaded last, once
+    pub fn take_texture(&mut self) -> Option<(String, Image)> {
+        self.texture.take()
+    }
+
+    pub fn gui(&mut self, ui: &mut egui::Ui) -> Option<Shape> {
+        let mut shape = self.picker.gui(ui).and_then(|file| self.load(file));
+        if ui.add(egui::Slider::new(&mut self.tolerance, 0.0001..=0.05).logarithmic(true).text("Curve tolerance")).changed() {
+            self.error = None;
+            shape = self.reparse();
+        }
+        if let Some(file) = &self.file {
+            ui.label(format!("Loaded {}", file.name));
+        }
+        if let Some(e) = &self.error {
+            ui.colored_label(ui.visuals().warn_fg_color, e);
+        }
+        shape
+    }
+}
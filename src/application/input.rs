@@ -8,6 +8,7 @@ pub struct Input {
     pub current_mouse_pos: PhysicalPosition<f64>,
     pub last_mouse_pos: PhysicalPosition<f64>,
     pub clicking: [bool;3], // left, right, middle
+    pub just_pressed: [bool;3], // buttons pressed since the last update
     pub mouse_delta: (f64, f64),
     pub scroll_delta: f64,
 }
@@ -18,6 +19,7 @@ impl Input {
             current_mouse_pos: PhysicalPosition::default(),
             last_mouse_pos: PhysicalPosition::default(),
             clicking: [false;3],
+            just_pressed: [false;3],
             mouse_delta: (0.0, 0.0),
             scroll_delta: 0.0
         }
@@ -53,6 +55,7 @@ impl Input {
                     _ => unimplemented!(),
                 };
                 let val = *state == winit::event::ElementState::Pressed;
+                self.just_pressed[idx] |= val && !self.clicking[idx];
                 self.clicking[idx] = val;
                 true
             }
@@ -90,6 +93,7 @@ impl Input {
     pub fn update(&mut self) {
         self.mouse_delta = (0.0, 0.0);
         self.scroll_delta = 0.0;
+        self.just_pressed = [false;3];
         self.last_mouse_pos = self.current_mouse_pos;
     }
 }
@@ -0,0 +1,518 @@
+use vek::{Mat4, Vec2, Vec3, Vec4};
+
+// matrices with a condition number above this are treated as singular, since f32 can't
+// represent their inverse with any useful accuracy
+pub const ILL_CONDITIONED: f64 = 1e6;
+
+type Mat4x4 = [[f64; 4]; 4]; // row major, double precision working copy
+
+fn to_rows(m: &Mat4<f32>) -> Mat4x4 {
+    m.map(|x| x as f64).into_row_arrays()
+}
+
+fn from_rows(rows: Mat4x4) -> Mat4<f32> {
+    Mat4::from_row_arrays(rows).map(|x| x as f32)
+}
+
+// maximum absolute column sum
+fn norm_1(m: &Mat4x4) -> f64 {
+    (0..4)
+        .map(|c| (0..4).map(|r| m[r][c].abs()).sum::<f64>())
+        .fold(0.0, f64::max)
+}
+
+// gauss-jordan elimination with partial pivoting
+fn gauss_jordan(mut a: Mat4x4) -> Option<Mat4x4> {
+    let scale = norm_1(&a);
+    if scale == 0.0 || !scale.is_finite() {
+        return None;
+    }
+    let mut inv = [[0.0; 4]; 4];
+    for (i, row) in inv.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+    for col in 0..4 {
+        let pivot = (col..4)
+            .max_by(|&x, &y| a[x][col].abs().total_cmp(&a[y][col].abs()))
+            .unwrap();
+        if a[pivot][col].abs() <= scale * f64::EPSILON {
+            return None;
+        }
+        a.swap(col, pivot);
+        inv.swap(col, pivot);
+        let p = a[col][col];
+        for c in 0..4 {
+            a[col][c] /= p;
+            inv[col][c] /= p;
+        }
+        for r in 0..4 {
+            if r == col {
+                continue;
+            }
+            let f = a[r][col];
+            for c in 0..4 {
+                a[r][c] -= f * a[col][c];
+                inv[r][c] -= f * inv[col][c];
+            }
+        }
+    }
+    Some(inv)
+}
+
+pub struct Inversion {
+    // None if the matrix is singular or too ill-conditioned to invert
+    pub inverse: Option<Mat4<f32>>,
+    // condition number in the 1-norm, infinite for singular matrices
+    pub condition: f64,
+}
+
+pub fn invert(m: &Mat4<f32>) -> Inversion {
+    let rows = to_rows(m);
+    match gauss_jordan(rows) {
+        Some(inv) => {
+            let condition = norm_1(&rows) * norm_1(&inv);
+            let usable = condition.is_finite() && condition <= ILL_CONDITIONED;
+            Inversion {
+                inverse: usable.then(|| from_rows(inv)),
+                condition,
+            }
+        }
+        None => Inversion {
+            inverse: None,
+            condition: f64::INFINITY,
+        },
+    }
+}
+
+pub fn is_finite(m: &Mat4<f32>) -> bool {
+    m.as_col_slice().iter().all(|x| x.is_finite())
+}
+
+fn identity() -> Mat4x4 {
+    let mut m = [[0.0; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+    m
+}
+
+fn mul(a: &Mat4x4, b: &Mat4x4) -> Mat4x4 {
+    let mut m = [[0.0; 4]; 4];
+    for (r, row) in m.iter_mut().enumerate() {
+        for (c, value) in row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[r][k] * b[k][c]).sum();
+        }
+    }
+    m
+}
+
+fn lin_comb(a: &Mat4x4, fa: f64, b: &Mat4x4, fb: f64) -> Mat4x4 {
+    let mut m = [[0.0; 4]; 4];
+    for r in 0..4 {
+        for c in 0..4 {
+            m[r][c] = fa * a[r][c] + fb * b[r][c];
+        }
+    }
+    m
+}
+
+// matrix exponential by scaling and squaring a truncated taylor series
+fn exp_rows(a: &Mat4x4) -> Mat4x4 {
+    let norm = norm_1(a);
+    let squarings = if norm > 0.5 { (norm / 0.5).log2().ceil() as i32 } else { 0 };
+    let scaled = lin_comb(a, 0.5f64.powi(squarings), a, 0.0);
+    let mut result = identity();
+    let mut term = identity();
+    for k in 1..=20 {
+        term = lin_comb(&mul(&term, &scaled), 1.0 / k as f64, &term, 0.0);
+        result = lin_comb(&result, 1.0, &term, 1.0);
+    }
+    for _ in 0..squarings {
+        result = mul(&result, &result);
+    }
+    result
+}
+
+pub fn exp(m: &Mat4<f32>) -> Mat4<f32> {
+    from_rows(exp_rows(&to_rows(m)))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl std::ops::Add for Complex {
+    type Output = Complex;
+    fn add(self, o: Complex) -> Complex {
+        Complex { re: self.re + o.re, im: self.im + o.im }
+    }
+}
+
+impl std::ops::Sub for Complex {
+    type Output = Complex;
+    fn sub(self, o: Complex) -> Complex {
+        Complex { re: self.re - o.re, im: self.im - o.im }
+    }
+}
+
+impl std::ops::Mul for Complex {
+    type Output = Complex;
+    fn mul(self, o: Complex) -> Complex {
+        Complex { re: self.re * o.re - self.im * o.im, im: self.re * o.im + self.im * o.re }
+    }
+}
+
+impl std::ops::Div for Complex {
+    type Output = Complex;
+    fn div(self, o: Complex) -> Complex {
+        let d = o.re * o.re + o.im * o.im;
+        Complex { re: (self.re * o.re + self.im * o.im) / d, im: (self.im * o.re - self.re * o.im) / d }
+    }
+}
+
+impl Complex {
+    pub const ZERO: Complex = Complex { re: 0.0, im: 0.0 };
+    pub const ONE: Complex = Complex { re: 1.0, im: 0.0 };
+
+    pub fn abs(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn from_point(p: Vec2<f32>) -> Complex {
+        Complex { re: p.x as f64, im: p.y as f64 }
+    }
+
+    pub fn to_point(self) -> Vec2<f32> {
+        Vec2::new(self.re as f32, self.im as f32)
+    }
+}
+
+impl std::fmt::Display for Complex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let precision = f.precision().unwrap_or(3);
+        if self.im.abs() < 1e-6 * self.abs().max(1.0) {
+            write!(f, "{:.*}", precision, self.re)
+        } else {
+            let sign = if self.im < 0.0 { '-' } else { '+' };
+            write!(f, "{:.*} {} {:.*}i", precision, self.re, sign, precision, self.im.abs())
+        }
+    }
+}
+
+// eigenvalues as the roots of the characteristic polynomial, found with durand-kerner
+pub fn eigenvalues(m: &Mat4<f32>) -> [Complex; 4] {
+    let a = to_rows(m);
+    // faddeev-leverrier gives the coefficients of det(λI - A) = λ⁴ + c[3]λ³ + c[2]λ² + c[1]λ + c[0]
+    let mut c = [0.0; 5];
+    c[4] = 1.0;
+    let mut mk = [[0.0; 4]; 4];
+    for k in 1..=4 {
+        mk = lin_comb(&mul(&a, &mk), 1.0, &identity(), c[5 - k]);
+        let am = mul(&a, &mk);
+        c[4 - k] = -(0..4).map(|i| am[i][i]).sum::<f64>() / k as f64;
+    }
+    let poly = |z: Complex| {
+        c.iter().rev().fold(Complex { re: 0.0, im: 0.0 }, |acc, &coeff| acc * z + Complex { re: coeff, im: 0.0 })
+    };
+    let mut roots = [Complex { re: 0.4, im: 0.9 }; 4];
+    for i in 1..4 {
+        roots[i] = roots[i - 1] * roots[0];
+    }
+    for _ in 0..500 {
+        for i in 0..4 {
+            let denominator = (0..4)
+                .filter(|&j| j != i)
+                .fold(Complex { re: 1.0, im: 0.0 }, |acc, j| acc * (roots[i] - roots[j]));
+            if denominator.abs() > 0.0 {
+                roots[i] = roots[i] - poly(roots[i]) / denominator;
+            }
+        }
+    }
+    roots
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogError {
+    Singular,
+    NegativeEigenvalue(f64),
+    NoConvergence,
+}
+
+impl std::fmt::Display for LogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogError::Singular => write!(f, "The matrix is singular, so it has no logarithm"),
+            LogError::NegativeEigenvalue(lambda) => write!(
+                f,
+                "The matrix has the negative real eigenvalue {lambda:.3}, so it has no real principal logarithm"
+            ),
+            LogError::NoConvergence => write!(f, "The matrix logarithm did not converge"),
+        }
+    }
+}
+
+// square root by the denman-beavers iteration, which converges to the principal root
+fn sqrt_rows(a: &Mat4x4) -> Option<Mat4x4> {
+    let mut y = *a;
+    let mut z = identity();
+    for _ in 0..100 {
+        let y_inv = gauss_jordan(y)?;
+        let z_inv = gauss_jordan(z)?;
+        let next = lin_comb(&y, 0.5, &z_inv, 0.5);
+        z = lin_comb(&z, 0.5, &y_inv, 0.5);
+        let step = norm_1(&lin_comb(&next, 1.0, &y, -1.0));
+        y = next;
+        if step <= 1e-13 * norm_1(&y) {
+            return Some(y);
+        }
+    }
+    None
+}
+
+// principal real logarithm by inverse scaling and squaring
+fn log_rows(a: &Mat4x4, eigenvalues: &[Complex; 4]) -> Result<Mat4x4, LogError> {
+    let scale = eigenvalues.iter().map(|l| l.abs()).fold(0.0, f64::max);
+    if gauss_jordan(*a).is_none() || eigenvalues.iter().any(|l| l.abs() <= 1e-9 * scale) {
+        return Err(LogError::Singular);
+    }
+    if let Some(lambda) = eigenvalues.iter().find(|l| l.re < 0.0 && l.im.abs() <= 1e-6 * l.abs()) {
+        return Err(LogError::NegativeEigenvalue(lambda.re));
+    }
+    let mut x = *a;
+    let mut roots = 0;
+    while norm_1(&lin_comb(&x, 1.0, &identity(), -1.0)) > 0.25 {
+        if roots >= 64 {
+            return Err(LogError::NoConvergence);
+        }
+        x = sqrt_rows(&x).ok_or(LogError::NoConvergence)?;
+        roots += 1;
+    }
+    // log(I + E) = E - E²/2 + E³/3 - ...
+    let e = lin_comb(&x, 1.0, &identity(), -1.0);
+    let mut result = [[0.0; 4]; 4];
+    let mut power = identity();
+    for k in 1..=40 {
+        power = mul(&power, &e);
+        let sign = if k % 2 == 1 { 1.0 } else { -1.0 };
+        result = lin_comb(&result, 1.0, &power, sign / k as f64);
+    }
+    Ok(lin_comb(&result, 2f64.powi(roots), &result, 0.0))
+}
+
+// real matrix power A^s = exp(s log A)
+pub fn power(m: &Mat4<f32>, s: f32) -> Result<Mat4<f32>, LogError> {
+    let log = log_rows(&to_rows(m), &eigenvalues(m))?;
+    Ok(from_rows(exp_rows(&lin_comb(&log, s as f64, &log, 0.0))))
+}
+
+// transform a point in the XY plane, including the perspective divide
+pub fn transform_point_2d(m: &Mat4<f32>, p: Vec2<f32>) -> Vec2<f32> {
+    let v = *m * Vec4::new(p.x, p.y, 0.0, 1.0);
+    v.xy() / v.w
+}
+
+// transform a point in space, including the perspective divide
+pub fn transform_point(m: &Mat4<f32>, p: Vec3<f32>) -> Vec3<f32> {
+    let v = *m * Vec4::from_point(p);
+    v.xyz() / v.w
+}
+
+// eigenvalues of the 2x2 linear part of a matrix acting on the XY plane
+pub fn eigenvalues_2d(m: &Mat4<f32>) -> [Complex; 2] {
+    let (a, b, c, d) = (m[(0, 0)] as f64, m[(0, 1)] as f64, m[(1, 0)] as f64, m[(1, 1)] as f64);
+    let half_trace = (a + d) / 2.0;
+    let discriminant = half_trace * half_trace - (a * d - b * c);
+    if discriminant >= 0.0 {
+        let root = discriminant.sqrt();
+        [Complex { re: half_trace + root, im: 0.0 }, Complex { re: half_trace - root, im: 0.0 }]
+    } else {
+        let root = (-discriminant).sqrt();
+        [Complex { re: half_trace, im: root }, Complex { re: half_trace, im: -root }]
+    }
+}
+
+// solve the 2x2 system [[a, b], [c, d]] x = rhs, None if it is singular
+pub fn solve_2d(a: f32, b: f32, c: f32, d: f32, rhs: Vec2<f32>) -> Option<Vec2<f32>> {
+    let det = a * d - b * c;
+    if det.abs() <= f32::EPSILON * (a.abs() + b.abs() + c.abs() + d.abs()).powi(2) {
+        return None;
+    }
+    Some(Vec2::new(d * rhs.x - b * rhs.y, a * rhs.y - c * rhs.x) / det)
+}
+
+// solve the square system ax = b by gaussian elimination with partial pivoting
+pub fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    let scale = a.iter().flatten().fold(0.0, |acc: f64, x| acc.max(x.abs()));
+    if scale == 0.0 || !scale.is_finite() {
+        return None;
+    }
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&x, &y| a[x][col].abs().total_cmp(&a[y][col].abs()))
+            .unwrap();
+        if a[pivot][col].abs() <= scale * 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        let pivot_row = a[col].clone();
+        for r in col + 1..n {
+            let f = a[r][col] / pivot_row[col];
+            for (x, p) in a[r][col..].iter_mut().zip(&pivot_row[col..]) {
+                *x -= f * p;
+            }
+            b[r] -= f * b[col];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for r in (0..n).rev() {
+        let sum: f64 = (r + 1..n).map(|c| a[r][c] * x[c]).sum();
+        x[r] = (b[r] - sum) / a[r][r];
+    }
+    Some(x)
+}
+
+// embed the 3x3 homography h (acting on (x, y, 1)) into a 4x4 matrix acting on (x, y, z, 1),
+// so the perspective divide happens on the gpu
+pub fn embed_homography(h: [[f64; 3]; 3]) -> Mat4<f32> {
+    Mat4::from_row_arrays([
+        [h[0][0], h[0][1], 0.0, h[0][2]],
+        [h[1][0], h[1][1], 0.0, h[1][2]],
+        [0.0, 0.0, 1.0, 0.0],
+        [h[2][0], h[2][1], 0.0, h[2][2]],
+    ])
+    .map(|x| x as f32)
+}
+
+// the projective map taking each source point to the matching target point
+pub fn homography(source: &[Vec2<f32>; 4], target: &[Vec2<f32>; 4]) -> Option<[[f64; 3]; 3]> {
+    let mut a = Vec::with_capacity(8);
+    let mut b = Vec::with_capacity(8);
+    for (s, t) in source.iter().zip(target.iter()) {
+        let (x, y, u, v) = (s.x as f64, s.y as f64, t.x as f64, t.y as f64);
+        a.push(vec![x, y, 1.0, 0.0, 0.0, 0.0, -x * u, -y * u]);
+        b.push(u);
+        a.push(vec![0.0, 0.0, 0.0, x, y, 1.0, -x * v, -y * v]);
+        b.push(v);
+    }
+    let h = solve(a, b)?;
+    let mut h = [[h[0], h[1], h[2]], [h[3], h[4], h[5]], [h[6], h[7], 1.0]];
+    // the same map scaled by -1 would put everything behind the camera (w < 0), so keep w positive
+    // around the source points
+    let centroid = source.iter().fold(Vec2::zero(), |acc, p| acc + *p) / 4.0;
+    if h[2][0] * centroid.x as f64 + h[2][1] * centroid.y as f64 + h[2][2] < 0.0 {
+        h.iter_mut().flatten().for_each(|x| *x = -*x);
+    }
+    Some(h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &Mat4<f32>, b: &Mat4<f32>, tolerance: f32) {
+        let error = (*a - *b).into_row_array().iter().fold(0.0f32, |acc, x| acc.max(x.abs()));
+        assert!(error <= tolerance, "{a:?} differs from {b:?} by {error}");
+    }
+
+    // a well conditioned matrix with a rotation, scaling, shear and translation in it
+    fn sample() -> Mat4<f32> {
+        Mat4::from_row_arrays([
+            [1.2, 0.3, -0.2, 0.5],
+            [-0.4, 0.9, 0.1, -1.0],
+            [0.2, 0.1, 1.1, 2.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // each expected eigenvalue matches a different one found, in any order
+    fn assert_eigenvalues(m: &Mat4<f32>, expected: [Complex; 4], tolerance: f64) {
+        let found = eigenvalues(m);
+        let mut unmatched = found.to_vec();
+        for e in expected {
+            let closest = (0..unmatched.len()).min_by(|&i, &j| (unmatched[i] - e).abs().total_cmp(&(unmatched[j] - e).abs())).unwrap();
+            assert!((unmatched[closest] - e).abs() <= tolerance, "eigenvalues {found:?}, expected {expected:?}");
+            unmatched.remove(closest);
+        }
+    }
+
+    #[test]
+    fn inverse_times_matrix_is_identity() {
+        let m = sample();
+        let inverse = invert(&m).inverse.unwrap();
+        assert_close(&(inverse * m), &Mat4::identity(), 1e-5);
+        assert_close(&(m * inverse), &Mat4::identity(), 1e-5);
+    }
+
+    #[test]
+    fn singular_and_ill_conditioned_have_no_inverse() {
+        let singular = Mat4::scaling_3d(Vec3::new(1.0, 0.0, 1.0));
+        let inversion = invert(&singular);
+        assert!(inversion.inverse.is_none());
+        assert_eq!(inversion.condition, f64::INFINITY);
+
+        let ill_conditioned = Mat4::scaling_3d(Vec3::new(1.0, 1e-7, 1.0));
+        let inversion = invert(&ill_conditioned);
+        assert!(inversion.inverse.is_none());
+        assert!(inversion.condition > ILL_CONDITIONED);
+    }
+
+    #[test]
+    fn exp_undoes_log() {
+        let m = sample();
+        let rows = to_rows(&m);
+        let log = log_rows(&rows, &eigenvalues(&m)).unwrap();
+        assert_close(&from_rows(exp_rows(&log)), &m, 1e-5);
+    }
+
+    #[test]
+    fn square_of_square_root() {
+        let m = sample();
+        let root = power(&m, 0.5).unwrap();
+        assert_close(&(root * root), &m, 1e-5);
+    }
+
+    #[test]
+    fn log_of_reflection_fails() {
+        let reflection = Mat4::scaling_3d(Vec3::new(-1.0, 1.0, 1.0));
+        assert_eq!(power(&reflection, 0.5), Err(LogError::NegativeEigenvalue(-1.0)));
+        assert_eq!(power(&Mat4::scaling_3d(Vec3::new(1.0, 0.0, 1.0)), 0.5), Err(LogError::Singular));
+    }
+
+    #[test]
+    fn eigenvalues_of_rotation_and_shear() {
+        let angle = 0.7f64;
+        let rotation = Mat4::rotation_z(angle as f32);
+        let (cos, sin) = (angle.cos(), angle.sin());
+        let expected = [Complex { re: cos, im: sin }, Complex { re: cos, im: -sin }, Complex::ONE, Complex::ONE];
+        assert_eigenvalues(&rotation, expected, 1e-6);
+
+        // a repeated eigenvalue only converges to about the fourth root of the precision
+        let shear = Mat4::from_row_arrays([
+            [1.0, 0.5, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        assert_eigenvalues(&shear, [Complex::ONE; 4], 1e-3);
+    }
+
+    #[test]
+    fn homography_maps_corners_onto_targets() {
+        let source = [Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0), Vec2::new(0.0, 1.0)];
+        let target = [Vec2::new(-1.0, -0.5), Vec2::new(2.0, 0.0), Vec2::new(1.5, 1.8), Vec2::new(-0.5, 1.0)];
+        let m = embed_homography(homography(&source, &target).unwrap());
+        for (s, t) in source.iter().zip(target) {
+            let mapped = transform_point_2d(&m, *s);
+            assert!(mapped.distance(t) <= 1e-5, "{s:?} went to {mapped:?} instead of {t:?}");
+            // the corners stay in front of the camera
+            assert!((m * Vec4::new(s.x, s.y, 0.0, 1.0)).w > 0.0);
+        }
+        // three collinear corners have no homography
+        let collinear = [Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(2.0, 0.0), Vec2::new(0.0, 1.0)];
+        assert!(homography(&collinear, &target).is_none());
+    }
+}
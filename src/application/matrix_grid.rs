@@ -0,0 +1,41 @@
+use vek::Mat4;
+
+// edit each element of a matrix with a dragvalue, hiding the z row and column unless asked for the full matrix.
+// a transposed matrix is shown as it acts on row vectors
+pub fn matrix_drag_grid(ui: &mut egui::Ui, id: impl std::hash::Hash, mat: &mut Mat4<f32>, show_full_matrix: bool, transposed: bool) {
+    let values = mat.as_mut_col_slice();
+    egui::Grid::new(id).show(ui, |ui| {
+        for y in 0..4usize {
+            if !show_full_matrix && y == 2 {
+                continue;
+            }
+            for x in 0..4usize {
+                if !show_full_matrix && x == 2 {
+                    continue;
+                }
+                let index = if transposed { y*4 + x } else { x*4 + y };
+                ui.add(egui::DragValue::new(&mut values[index]).speed(0.01));
+            }
+            ui.end_row();
+        }
+    });
+}
+
+// read-only view of a matrix, laid out like the editable custom matrix grid
+pub fn matrix_value_grid(ui: &mut egui::Ui, id: impl std::hash::Hash, mat: &Mat4<f32>, show_full_matrix: bool, transposed: bool) {
+    egui::Grid::new(id).show(ui, |ui| {
+        for y in 0..4usize {
+            if !show_full_matrix && y == 2 {
+                continue;
+            }
+            for x in 0..4usize {
+                if !show_full_matrix && x == 2 {
+                    continue;
+                }
+                let value = if transposed { mat[(x, y)] } else { mat[(y, x)] };
+                ui.label(format!("{value:.3}"));
+            }
+            ui.end_row();
+        }
+    });
+}
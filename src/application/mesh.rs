@@ -0,0 +1,242 @@
+use vek::{Vec2, Vec3, Vec4};
+
+use crate::renderer::Vertex;
+
+// the unit quad centered at the origin, split into n by n cells so non-linear maps can bend it
+pub fn subdivided_quad(n: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let n = n.max(1);
+    let mut vertices = Vec::new();
+    for j in 0..=n {
+        for i in 0..=n {
+            let u = i as f32 / n as f32;
+            let v = j as f32 / n as f32;
+            vertices.push(Vertex::new(Vec4::new(u - 0.5, v - 0.5, 0.0, 1.0), Vec4::new(u, v, 0.0, 0.0)));
+        }
+    }
+    let mut indices = Vec::new();
+    let row = n + 1;
+    for j in 0..n {
+        for i in 0..n {
+            let corner = j * row + i;
+            // same winding as the original two triangle quad
+            indices.extend_from_slice(&[corner, corner + 1, corner + row, corner + 1, corner + row + 1, corner + row]);
+        }
+    }
+    (vertices, indices)
+}
+
+// the attributes of one vertex of a loaded mesh, before it is fitted into the unit cube
+pub struct MeshVertex {
+    pub position: Vec3<f32>,
+    pub normal: Option<Vec3<f32>>,
+    pub uv: Option<Vec2<f32>>,
+    pub color: Option<Vec4<f32>>,
+}
+
+// center the mesh and scale it to fit the unit cube. normals darken the faces turned away from a
+// light fixed to the model, and vertices without uvs get them from the bounding box like other shapes
+pub fn fit_mesh(vertices: &[MeshVertex]) -> Vec<Vertex> {
+    let light = Vec3::new(0.3, 0.5, 1.0).normalized();
+    let (min, max) = vertices.iter().fold((Vec3::broadcast(f32::INFINITY), Vec3::broadcast(f32::NEG_INFINITY)), |(min, max), v| {
+        (Vec3::partial_min(min, v.position), Vec3::partial_max(max, v.position))
+    });
+    let center = (min + max) / 2.0;
+    let extent = (max - min).reduce_partial_max().max(f32::EPSILON);
+    let size = Vec2::partial_max(max.xy() - min.xy(), Vec2::broadcast(f32::EPSILON));
+    vertices
+        .iter()
+        .map(|v| {
+            let p = (v.position - center) / extent;
+            let uv = v.uv.unwrap_or((v.position.xy() - min.xy()) / size);
+            let shade = v.normal.and_then(|n| n.try_normalized()).map_or(0.0, |n| 0.6 * (1.0 - n.dot(light).max(0.0)));
+            Vertex::new(Vec4::new(p.x, p.y, p.z, 1.0), Vec4::new(uv.x, uv.y, shade, 0.0)).with_color(v.color.unwrap_or(Vec4::zero()))
+        })
+        .collect()
+}
+
+fn cross(a: Vec2<f32>, b: Vec2<f32>, c: Vec2<f32>) -> f32 {
+    (b - a).x * (c - a).y - (b - a).y * (c - a).x
+}
+
+// twice the signed area, positive for counter clockwise outlines
+pub fn signed_area(points: &[Vec2<f32>]) -> f32 {
+    (0..points.len()).map(|i| {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        a.x * b.y - b.x * a.y
+    }).sum()
+}
+
+// proper crossings only, touching ends or overlapping collinear segments don't count
+fn segments_cross(a: Vec2<f32>, b: Vec2<f32>, c: Vec2<f32>, d: Vec2<f32>) -> bool {
+    cross(a, b, c) * cross(a, b, d) < 0.0 && cross(c, d, a) * cross(c, d, b) < 0.0
+}
+
+// even-odd test of a point against a closed outline
+pub fn contains(outline: &[Vec2<f32>], p: Vec2<f32>) -> bool {
+    let mut inside = false;
+    for i in 0..outline.len() {
+        let (a, b) = (outline[i], outline[(i + 1) % outline.len()]);
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// cut each hole into the outline along a bridge to one of its corners, so the result is a single
+// outline (touching itself along the bridges) that can be triangulated
+pub fn bridge_holes(outline: &[Vec2<f32>], holes: &[Vec<Vec2<f32>>]) -> Vec<Vec2<f32>> {
+    let mut merged = outline.to_vec();
+    if signed_area(&merged) < 0.0 {
+        merged.reverse();
+    }
+    // holes run the other way round, and the rightmost ones go first so bridges don't cross later holes
+    let mut holes = holes
+        .iter()
+        .filter(|hole| hole.len() >= 3)
+        .map(|hole| {
+            let mut hole = hole.clone();
+            if signed_area(&hole) > 0.0 {
+                hole.reverse();
+            }
+            hole
+        })
+        .collect::<Vec<_>>();
+    let rightmost = |hole: &[Vec2<f32>]| (0..hole.len()).max_by(|a, b| hole[*a].x.total_cmp(&hole[*b].x)).unwrap_or(0);
+    holes.sort_by(|a, b| b[rightmost(b)].x.total_cmp(&a[rightmost(a)].x));
+    for (n, hole) in holes.iter().enumerate() {
+        let start = rightmost(hole);
+        let from = hole[start];
+        let blocked = |to: Vec2<f32>| {
+            let edges = |outline: &[Vec2<f32>]| (0..outline.len()).map(|i| (outline[i], outline[(i + 1) % outline.len()])).collect::<Vec<_>>();
+            std::iter::once(&merged[..])
+                .chain(holes[n..].iter().map(|h| &h[..]))
+                .flat_map(edges)
+                .any(|(a, b)| segments_cross(from, to, a, b))
+        };
+        let mut candidates = (0..merged.len()).collect::<Vec<_>>();
+        candidates.sort_by(|a, b| merged[*a].distance_squared(from).total_cmp(&merged[*b].distance_squared(from)));
+        let Some(bridge) = candidates.into_iter().find(|i| !blocked(merged[*i])) else {
+            continue;
+        };
+        let around = hole[start..].iter().chain(&hole[..=start]).copied();
+        let tail = merged.split_off(bridge);
+        merged.extend(std::iter::once(tail[0]).chain(around).chain(tail));
+    }
+    merged
+}
+
+// whether two edges that don't share a corner intersect
+fn crosses_itself(points: &[Vec2<f32>]) -> bool {
+    let n = points.len();
+    let edge = |i: usize| (points[i], points[(i + 1) % n]);
+    (0..n).any(|i| {
+        (i + 2..n).filter(|&j| (j + 1) % n != i).any(|j| {
+            let ((a, b), (c, d)) = (edge(i), edge(j));
+            segments_cross(a, b, c, d)
+        })
+    })
+}
+
+// ear clipping of a simple polygon, concave ones included. gives indices into `points`,
+// or None if the outline crosses itself or has no area
+pub fn triangulate(points: &[Vec2<f32>]) -> Option<Vec<u32>> {
+    let area = signed_area(points);
+    if points.len() < 3 || area.abs() <= f32::EPSILON || crosses_itself(points) {
+        return None;
+    }
+    // walk the outline counter clockwise, so ears are the corners turning left
+    let mut remaining = (0..points.len() as u32).collect::<Vec<_>>();
+    if area < 0.0 {
+        remaining.reverse();
+    }
+    let p = |i: u32| points[i as usize];
+    let mut indices = Vec::new();
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]);
+            if cross(p(a), p(b), p(c)) <= 0.0 {
+                return false;
+            }
+            // no other corner may lie inside (or on the edge of) the ear
+            !remaining.iter().any(|&q| {
+                q != a && q != b && q != c && p(q) != p(a) && p(q) != p(b) && p(q) != p(c)
+                    && cross(p(a), p(b), p(q)) >= 0.0 && cross(p(b), p(c), p(q)) >= 0.0 && cross(p(c), p(a), p(q)) >= 0.0
+            })
+        })?;
+        indices.extend_from_slice(&[remaining[(ear + n - 1) % n], remaining[ear], remaining[(ear + 1) % n]]);
+        remaining.remove(ear);
+    }
+    indices.extend_from_slice(&remaining);
+    Some(indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(coords: &[(f32, f32)]) -> Vec<Vec2<f32>> {
+        coords.iter().map(|&(x, y)| Vec2::new(x, y)).collect()
+    }
+
+    // triangulates the outline, checking every triangle turns counter clockwise, and gives the
+    // number of triangles and their total area
+    fn triangles_and_area(outline: &[Vec2<f32>]) -> (usize, f32) {
+        let indices = triangulate(outline).unwrap();
+        assert!(indices.len().is_multiple_of(3));
+        let mut area = 0.0;
+        for triangle in indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| outline[triangle[i] as usize]);
+            let twice = cross(a, b, c);
+            assert!(twice >= 0.0, "triangle {triangle:?} is clockwise");
+            area += twice / 2.0;
+        }
+        (indices.len() / 3, area)
+    }
+
+    #[test]
+    fn triangulates_convex() {
+        let hexagon = (0..6).map(|i| Vec2::new(1.0, 0.0).rotated_z(i as f32 * std::f32::consts::FRAC_PI_3)).collect::<Vec<_>>();
+        let (count, area) = triangles_and_area(&hexagon);
+        assert_eq!(count, 4);
+        assert!((area - 1.5 * 3f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn triangulates_concave() {
+        let l_shape = points(&[(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0)]);
+        assert_eq!(triangles_and_area(&l_shape), (4, 3.0));
+        let arrow = points(&[(0.0, 0.0), (2.0, 1.0), (0.0, 2.0), (0.5, 1.0)]);
+        assert_eq!(triangles_and_area(&arrow), (2, 1.5));
+    }
+
+    #[test]
+    fn triangulates_clockwise() {
+        let mut l_shape = points(&[(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0)]);
+        l_shape.reverse();
+        assert!(signed_area(&l_shape) < 0.0);
+        assert_eq!(triangles_and_area(&l_shape), (4, 3.0));
+    }
+
+    #[test]
+    fn triangulates_polygon_with_hole() {
+        let outline = points(&[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)]);
+        // given counter clockwise like the outline, bridge_holes turns it round
+        let hole = points(&[(1.0, 1.0), (3.0, 1.0), (3.0, 3.0), (1.0, 3.0)]);
+        let merged = bridge_holes(&outline, &[hole]);
+        // both outlines plus the two corners repeated at the ends of the bridge
+        assert_eq!(merged.len(), 10);
+        let (count, area) = triangles_and_area(&merged);
+        assert_eq!(count, 8);
+        assert!((area - 12.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn rejects_crossing_and_flat_outlines() {
+        let bow_tie = points(&[(0.0, 0.0), (1.0, 1.0), (1.0, 0.0), (0.0, 1.0)]);
+        assert!(triangulate(&bow_tie).is_none());
+        let line = points(&[(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)]);
+        assert!(triangulate(&line).is_none());
+    }
+}
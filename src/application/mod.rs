@@ -1,4 +1,30 @@
+mod basis;
 mod camera;
+mod complex;
+mod drawing;
+mod dynamics;
+mod export;
+mod expr;
+mod files;
+mod fitting;
+mod gizmo;
+mod gltf_mesh;
+mod ifs;
+mod import;
 mod input;
+mod linalg;
+mod matrix_grid;
+mod mesh;
+mod obj;
+mod overlay;
+mod points;
+mod probe;
+mod scene;
+mod shapes;
+mod stack;
+mod svg;
+mod texture;
 mod state;
-pub use state::ApplicationState;
+mod viewport;
+mod warp;
+pub use state::ApplicationState;
\ No newline at end of file
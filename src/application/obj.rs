@@ -0,0 +1,76 @@
+use vek::{Vec2, Vec3};
+
+use super::mesh::{self, MeshVertex};
+use crate::renderer::Vertex;
+
+fn floats<const N: usize>(args: &[&str], line: usize) -> Result<[f32; N], String> {
+    let mut values = [0.0; N];
+    for (i, value) in values.iter_mut().enumerate() {
+        let arg = args.get(i).ok_or(format!("Line {line}: expected {N} numbers"))?;
+        *value = arg.parse().map_err(|_| format!("Line {line}: invalid number \"{arg}\""))?;
+    }
+    Ok(values)
+}
+
+// an index into one of the lists so far, 1 based or negative from the end
+fn resolve(index: &str, len: usize, line: usize) -> Result<usize, String> {
+    let index: i64 = index.parse().map_err(|_| format!("Line {line}: invalid index \"{index}\""))?;
+    let resolved = if index < 0 { len as i64 + index } else { index - 1 };
+    if (0..len as i64).contains(&resolved) {
+        Ok(resolved as usize)
+    } else {
+        Err(format!("Line {line}: index {index} is out of range"))
+    }
+}
+
+// wavefront obj: positions, uvs and normals, with polygonal faces split into fans. materials are ignored
+pub fn parse(source: &str) -> Result<(Vec<Vertex>, Vec<u32>), String> {
+    let (mut positions, mut uvs, mut normals) = (Vec::new(), Vec::new(), Vec::new());
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    // each distinct position/uv/normal triple becomes one vertex
+    let mut seen = std::collections::HashMap::new();
+    for (n, line) in source.lines().enumerate() {
+        let n = n + 1;
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let args = words.collect::<Vec<_>>();
+        match keyword {
+            "v" => positions.push(Vec3::from(floats::<3>(&args, n)?)),
+            "vt" => uvs.push(Vec2::from(floats::<2>(&args, n)?)),
+            "vn" => normals.push(Vec3::from(floats::<3>(&args, n)?)),
+            "f" => {
+                if args.len() < 3 {
+                    return Err(format!("Line {n}: a face needs at least 3 vertices"));
+                }
+                let mut face = Vec::new();
+                for corner in &args {
+                    let mut parts = corner.split('/');
+                    let position = resolve(parts.next().unwrap_or_default(), positions.len(), n)?;
+                    let uv = parts.next().filter(|s| !s.is_empty()).map(|s| resolve(s, uvs.len(), n)).transpose()?;
+                    let normal = parts.next().filter(|s| !s.is_empty()).map(|s| resolve(s, normals.len(), n)).transpose()?;
+                    let index = *seen.entry((position, uv, normal)).or_insert_with(|| {
+                        vertices.push(MeshVertex {
+                            position: positions[position],
+                            normal: normal.map(|i| normals[i]),
+                            uv: uv.map(|i| uvs[i]),
+                            color: None,
+                        });
+                        vertices.len() as u32 - 1
+                    });
+                    face.push(index);
+                }
+                for i in 1..face.len() - 1 {
+                    indices.extend_from_slice(&[face[0], face[i], face[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+    if indices.is_empty() {
+        return Err("The file contains no faces".into());
+    }
+    Ok((mesh::fit_mesh(&vertices), indices))
+}
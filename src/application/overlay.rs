@@ -0,0 +1,39 @@
+use egui::{Painter, Pos2};
+use vek::Vec2;
+
+use super::{camera::Camera, viewport::Viewport};
+
+// draws on top of the rendered scene (but below windows), positioned in world space
+pub struct Overlay<'a> {
+    pub painter: Painter,
+    camera: &'a Camera,
+    viewport: Viewport,
+    pixels_per_point: f32,
+}
+
+impl<'a> Overlay<'a> {
+    pub fn new(ctx: &egui::Context, camera: &'a Camera, viewport: Viewport) -> Self {
+        let pixels_per_point = ctx.pixels_per_point();
+        let min = viewport.origin / pixels_per_point;
+        let max = (viewport.origin + viewport.size) / pixels_per_point;
+        let clip_rect = egui::Rect::from_min_max(Pos2::new(min.x, min.y), Pos2::new(max.x, max.y));
+        Self {
+            painter: ctx.layer_painter(egui::LayerId::background()).with_clip_rect(clip_rect),
+            camera,
+            viewport,
+            pixels_per_point,
+        }
+    }
+
+    pub fn to_screen(&self, world: Vec2<f32>) -> Pos2 {
+        let screen = (self.camera.world_to_screen(world, self.viewport.size) + self.viewport.origin) / self.pixels_per_point;
+        Pos2::new(screen.x, screen.y)
+    }
+
+    // corners (min, max) of the visible part of the world
+    pub fn visible_world(&self) -> (Vec2<f32>, Vec2<f32>) {
+        let a = self.camera.screen_to_world(Vec2::zero(), self.viewport.size);
+        let b = self.camera.screen_to_world(self.viewport.size, self.viewport.size);
+        (Vec2::partial_min(a, b), Vec2::partial_max(a, b))
+    }
+}
@@ -0,0 +1,340 @@
+use egui::{ecolor::HsvaGamma, Color32, Rgba};
+use vek::{Mat4, Vec3, Vec4};
+
+use super::{
+    files::{FilePicker, FileSaver, PickedFile},
+    svg,
+};
+use crate::renderer::{PointInstance, Vertex};
+
+const DEFAULT_COLOR: Color32 = Color32::from_rgb(230, 230, 230);
+const LABEL_NAMES: [&str; 6] = ["label", "class", "category", "group", "species", "name"];
+
+// a csv file split into fields, every row as long as the first one
+struct Table {
+    header: Option<Vec<String>>,
+    rows: Vec<Vec<String>>,
+    delimiter: char,
+}
+
+// which fields of a row make up a point
+#[derive(Clone, Copy, PartialEq)]
+struct Columns {
+    x: usize,
+    y: usize,
+    z: Option<usize>,
+    color: Option<usize>,
+    label: Option<usize>,
+}
+
+// fields may be quoted, with "" for a quote inside of them
+fn split_row(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields.into_iter().map(|field| field.trim().to_string()).collect()
+}
+
+fn is_number(field: &str) -> bool {
+    field.parse::<f32>().is_ok_and(f32::is_finite)
+}
+
+fn parse_table(source: &str) -> Result<Table, String> {
+    let mut lines = source.lines().filter(|line| !line.trim().is_empty());
+    let first = lines.next().ok_or("The file is empty")?;
+    // whichever separator the first line uses most
+    let delimiter = [',', ';', '\t'].into_iter().max_by_key(|d| first.matches(*d).count()).unwrap();
+    let mut rows = std::iter::once(first).chain(lines).map(|line| split_row(line, delimiter)).collect::<Vec<_>>();
+    // a header has names where the data below it has numbers
+    let header = match &rows[..] {
+        [first, second, ..] if first.iter().zip(second).any(|(a, b)| !is_number(a) && is_number(b)) => Some(rows.remove(0)),
+        _ => None,
+    };
+    let width = header.as_ref().unwrap_or(&rows[0]).len();
+    if let Some(i) = rows.iter().position(|row| row.len() != width) {
+        return Err(format!("Row {} has {} fields instead of {width}", i + 1 + header.is_some() as usize, rows[i].len()));
+    }
+    Ok(Table { header, rows, delimiter })
+}
+
+// columns named x, y and z, or else the first numeric ones. a column of colours or labels is
+// found by its name, or by what is in it
+fn guess_columns(table: &Table) -> Result<Columns, String> {
+    let names = table.header.as_ref().map(|header| header.iter().map(|name| name.to_lowercase()).collect::<Vec<_>>());
+    let named = |candidates: &[&str]| names.as_ref().and_then(|names| names.iter().position(|name| candidates.contains(&name.as_str())));
+    let first = &table.rows[0];
+    let numeric = (0..first.len()).filter(|i| is_number(&first[*i])).collect::<Vec<_>>();
+    let (x, y, z) = match (named(&["x"]), named(&["y"])) {
+        (Some(x), Some(y)) => (x, y, named(&["z"])),
+        _ => match numeric[..] {
+            [x, y, z, ..] => (x, y, Some(z)),
+            [x, y] => (x, y, None),
+            _ => return Err("Points need at least two columns of numbers".into()),
+        },
+    };
+    let text = (0..first.len()).filter(|i| !numeric.contains(i)).collect::<Vec<_>>();
+    let color = named(&["color", "colour"]).or_else(|| text.iter().copied().find(|i| matches!(svg::parse_color(&first[*i]), Ok(Some(_)))));
+    let label = named(&LABEL_NAMES).or_else(|| text.iter().copied().find(|i| Some(*i) != color));
+    Ok(Columns { x, y, z, color, label })
+}
+
+fn linear(color: Color32) -> Vec4<f32> {
+    Vec4::from(Rgba::from(color).to_array())
+}
+
+// evenly spread hues, so the first few labels are easy to tell apart
+fn label_color(i: usize) -> Color32 {
+    const GOLDEN_RATIO: f32 = 0.618_034;
+    HsvaGamma { h: (i as f32 * GOLDEN_RATIO).fract(), s: 0.7, v: 1.0, a: 1.0 }.into()
+}
+
+fn quote(field: &str, delimiter: char) -> String {
+    if field.contains(delimiter) || field.contains('"') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// the moved coordinates in place of the original ones, the other columns are kept as they are
+fn export(table: &Table, columns: Columns, positions: &[Vec3<f32>], map: impl Fn(Vec3<f32>) -> Vec3<f32>) -> String {
+    let moved = positions.iter().map(|p| map(*p)).collect::<Vec<_>>();
+    // 2d data gets a z column once the stack lifts it out of the plane
+    let with_z = columns.z.is_some() || moved.iter().any(|p| p.z.abs() > 1e-6);
+    let others = (0..table.rows[0].len()).filter(|i| ![Some(columns.x), Some(columns.y), columns.z].contains(&Some(*i))).collect::<Vec<_>>();
+    let line = |coordinates: Vec<String>, fields: &[String]| {
+        let fields = coordinates.into_iter().chain(others.iter().map(|i| quote(&fields[*i], table.delimiter)));
+        fields.collect::<Vec<_>>().join(&table.delimiter.to_string()) + "\n"
+    };
+    let mut csv = String::new();
+    if let Some(header) = &table.header {
+        let name = |column: Option<usize>, default: &str| quote(column.map_or(default, |i| &header[i]), table.delimiter);
+        let mut names = vec![name(Some(columns.x), "x"), name(Some(columns.y), "y")];
+        if with_z {
+            names.push(name(columns.z, "z"));
+        }
+        csv += &line(names, header);
+    }
+    for (p, row) in moved.iter().zip(&table.rows) {
+        let mut coordinates = vec![p.x.to_string(), p.y.to_string()];
+        if with_z {
+            coordinates.push(p.z.to_string());
+        }
+        csv += &line(coordinates, row);
+    }
+    csv
+}
+
+// a quad that each point draws as a round sprite
+pub fn sprite() -> (Vec<Vertex>, Vec<u32>) {
+    let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+    let vertices = corners
+        .iter()
+        .map(|&(x, y)| Vertex::new(Vec4::new(x, y, 0.0, 1.0), Vec4::new((x + 1.0) / 2.0, (y + 1.0) / 2.0, 0.0, 0.0)))
+        .collect();
+    (vertices, vec![0, 1, 2, 0, 2, 3])
+}
+
+// there is no depth buffer, so points further back have to be drawn first
+pub fn sort_back_to_front(instances: &mut [PointInstance], transform: Mat4<f32>) {
+    let depth = |instance: &PointInstance| {
+        let p = transform * Vec4::from_point(instance.position);
+        p.z / p.w
+    };
+    instances.sort_by(|a, b| depth(a).total_cmp(&depth(b)));
+}
+
+// points from a csv file, drawn as sprites and moved by the stack like the model
+pub struct PointCloud {
+    pub enabled: bool,
+    pub show_model: bool,
+    picker: FilePicker,
+    saver: FileSaver,
+    name: Option<String>,
+    table: Option<Table>,
+    columns: Option<Columns>,
+    radius: f32,
+    positions: Vec<Vec3<f32>>,
+    colors: Vec<Vec4<f32>>,
+    legend: Vec<(String, Color32)>,
+    error: Option<String>,
+    message: Option<String>, // what happened to the last export
+}
+
+impl PointCloud {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            show_model: false,
+            picker: FilePicker::new(&["csv", "tsv", "txt"]),
+            saver: FileSaver::new("transformed.csv"),
+            name: None,
+            table: None,
+            columns: None,
+            radius: 0.02,
+            positions: Vec::new(),
+            colors: Vec::new(),
+            legend: Vec::new(),
+            error: None,
+            message: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn is_3d(&self) -> bool {
+        self.columns.is_some_and(|columns| columns.z.is_some())
+    }
+
+    // returns true if there are new points
+    pub fn load(&mut self, file: Result<PickedFile, String>) -> bool {
+        self.error = None;
+        let loaded = file.and_then(|file| {
+            let table = parse_table(&String::from_utf8_lossy(&file.bytes))?;
+            let columns = guess_columns(&table)?;
+            Ok((file.name, table, columns))
+        });
+        match loaded {
+            Ok((name, table, columns)) => {
+                self.name = Some(name);
+                self.table = Some(table);
+                self.columns = Some(columns);
+                self.rebuild()
+            }
+            Err(e) => {
+                self.error = Some(e);
+                false
+            }
+        }
+    }
+
+    // read the points out of the table with the chosen columns
+    fn rebuild(&mut self) -> bool {
+        let (Some(table), Some(columns)) = (&self.table, self.columns) else {
+            return false;
+        };
+        let number = |row: usize, column: usize| {
+            let field = &table.rows[row][column];
+            field.parse::<f32>().ok().filter(|v| v.is_finite()).ok_or_else(|| format!("\"{field}\" in row {} is not a number", row + 1))
+        };
+        let positions = (0..table.rows.len())
+            .map(|row| Ok(Vec3::new(number(row, columns.x)?, number(row, columns.y)?, columns.z.map(|z| number(row, z)).transpose()?.unwrap_or(0.0))))
+            .collect::<Result<Vec<_>, String>>();
+        self.positions = match positions {
+            Ok(positions) => positions,
+            Err(e) => {
+                self.error = Some(e);
+                self.positions.clear();
+                return true;
+            }
+        };
+        // labels get a colour each, in the order they show up
+        self.legend.clear();
+        let mut colors = Vec::with_capacity(table.rows.len());
+        for row in &table.rows {
+            let given = columns.color.and_then(|i| svg::parse_color(&row[i]).ok().flatten());
+            let labelled = columns.label.map(|i| match self.legend.iter().find(|(label, _)| *label == row[i]) {
+                Some((_, color)) => *color,
+                None => {
+                    let color = label_color(self.legend.len());
+                    self.legend.push((row[i].clone(), color));
+                    color
+                }
+            });
+            colors.push(given.unwrap_or_else(|| linear(labelled.unwrap_or(DEFAULT_COLOR))));
+        }
+        self.colors = colors;
+        true
+    }
+
+    // the sprites, with the points moved by `map`
+    pub fn instances(&self, map: impl Fn(Vec3<f32>) -> Vec3<f32>) -> Vec<PointInstance> {
+        self.positions
+            .iter()
+            .zip(&self.colors)
+            .map(|(position, color)| PointInstance { position: map(*position), radius: self.radius, color: *color })
+            .collect()
+    }
+
+    fn column_combo(ui: &mut egui::Ui, label: &str, names: &[String], column: &mut Option<usize>, optional: bool) {
+        let selected = column.map_or("None", |i| names[i].as_str());
+        egui::ComboBox::from_label(label).selected_text(selected).show_ui(ui, |ui| {
+            if optional {
+                ui.selectable_value(column, None, "None");
+            }
+            for (i, name) in names.iter().enumerate() {
+                ui.selectable_value(column, Some(i), name);
+            }
+        });
+    }
+
+    // returns true when the sprites have to be rebuilt. `map` moves points like the stack does
+    pub fn gui(&mut self, ui: &mut egui::Ui, map: impl Fn(Vec3<f32>) -> Vec3<f32>) -> bool {
+        let mut changed = self.picker.gui(ui).is_some_and(|file| self.load(file));
+        if let Some(name) = &self.name {
+            ui.label(format!("Loaded {} points from {name}", self.positions.len()));
+        }
+        if let (Some(table), Some(columns)) = (&self.table, self.columns) {
+            let names = match &table.header {
+                Some(header) => header.clone(),
+                None => (1..=table.rows[0].len()).map(|i| format!("Column {i}")).collect(),
+            };
+            let (mut x, mut y, mut edited) = (Some(columns.x), Some(columns.y), columns);
+            Self::column_combo(ui, "x", &names, &mut x, false);
+            Self::column_combo(ui, "y", &names, &mut y, false);
+            Self::column_combo(ui, "z", &names, &mut edited.z, true);
+            Self::column_combo(ui, "Colour", &names, &mut edited.color, true);
+            Self::column_combo(ui, "Label", &names, &mut edited.label, true);
+            (edited.x, edited.y) = (x.unwrap_or(columns.x), y.unwrap_or(columns.y));
+            if edited != columns {
+                self.columns = Some(edited);
+                self.error = None;
+                changed |= self.rebuild();
+            }
+        }
+        changed |= ui.add(egui::Slider::new(&mut self.radius, 0.001..=1.0).logarithmic(true).text("Radius")).changed();
+        ui.checkbox(&mut self.show_model, "Show the model too");
+        if !self.legend.is_empty() {
+            ui.collapsing("Labels", |ui| {
+                for (label, color) in &self.legend {
+                    ui.colored_label(*color, format!("● {label}"));
+                }
+            });
+        }
+        if let Some(e) = &self.error {
+            ui.colored_label(ui.visuals().warn_fg_color, e);
+        }
+        if !self.is_empty() {
+            ui.separator();
+            ui.label("Export the transformed points");
+            let csv = || match (&self.table, self.columns) {
+                (Some(table), Some(columns)) => export(table, columns, &self.positions, &map),
+                _ => String::new(),
+            };
+            if ui.button("Copy as CSV").clicked() {
+                ui.ctx().copy_text(csv());
+                self.message = Some("Copied to the clipboard".into());
+            }
+            if let Some(saved) = self.saver.gui(ui, || csv().into_bytes()) {
+                self.message = Some(saved.unwrap_or_else(|e| e));
+            }
+            if let Some(message) = &self.message {
+                ui.label(message);
+            }
+        }
+        changed
+    }
+}
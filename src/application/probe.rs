@@ -0,0 +1,203 @@
+use egui::{Color32, Stroke};
+use vek::{Mat4, Vec2, Vec4};
+
+use super::{
+    overlay::Overlay,
+    stack::{application_order, is_linear, map_point, Convention, StackEntry},
+};
+
+const PROBE_COLORS: [Color32; 4] = [
+    Color32::from_rgb(255, 200, 60),
+    Color32::from_rgb(200, 120, 255),
+    Color32::from_rgb(60, 220, 200),
+    Color32::from_rgb(255, 110, 160),
+];
+
+// a point after one stage of the stack, along with the entry that was applied last (None for the input)
+struct Stage {
+    entry: Option<usize>,
+    position: Vec4<f32>,
+}
+
+// traces points through every partial product of the stack
+pub struct PointProbe {
+    pub enabled: bool,
+    pub placing: bool, // clicks on the canvas place probes
+    points: Vec<Vec2<f32>>,
+}
+
+// the point meets the active entries in the order the convention applies them
+fn stages(entries: &[StackEntry], convention: Convention, point: Vec2<f32>) -> Vec<Stage> {
+    let mut position = Vec4::new(point.x, point.y, 0.0, 1.0);
+    let mut stages = vec![Stage { entry: None, position }];
+    for idx in application_order(entries, convention) {
+        let entry = &entries[idx];
+        let (mat, interaction_type) = (&entry.matrix, &entry.interaction_type);
+        position = if is_linear(interaction_type) {
+            *mat * position
+        } else {
+            // non-linear entries act on the point itself, not its homogeneous coordinates
+            let p = map_point(entry, position.xy() / position.w);
+            Vec4::new(p.x, p.y, 0.0, 1.0)
+        };
+        stages.push(Stage { entry: Some(idx), position });
+    }
+    stages
+}
+
+fn stage_label(stage: &Stage) -> String {
+    match stage.entry {
+        Some(idx) => idx.to_string(),
+        None => "p".into(),
+    }
+}
+
+impl PointProbe {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            placing: false,
+            points: Vec::new(),
+        }
+    }
+
+    pub fn place(&mut self, world: Vec2<f32>) {
+        self.points.push(world);
+    }
+
+    pub fn gui(&mut self, ui: &mut egui::Ui, entries: &[StackEntry], convention: Convention) {
+        ui.checkbox(&mut self.placing, "Place probes on the canvas");
+        if self.points.is_empty() {
+            ui.label("No probes yet.");
+            return;
+        }
+        let mut remove = None;
+        for (n, point) in self.points.iter_mut().enumerate() {
+            let color = PROBE_COLORS[n % PROBE_COLORS.len()];
+            egui::CollapsingHeader::new(egui::RichText::new(format!("Probe {}", n + 1)).color(color))
+                .id_salt(("probe", n))
+                .default_open(true)
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut point.x).speed(0.01).prefix("x: "));
+                        ui.add(egui::DragValue::new(&mut point.y).speed(0.01).prefix("y: "));
+                        if ui.button("Remove").clicked() {
+                            remove = Some(n);
+                        }
+                    });
+                    egui::Grid::new(("probe_stages", n)).striped(true).show(ui, |ui| {
+                        for heading in ["Stage", "After", "x", "y", "z", "w"] {
+                            ui.strong(heading);
+                        }
+                        ui.end_row();
+                        for stage in stages(entries, convention, *point) {
+                            ui.label(stage_label(&stage));
+                            ui.label(stage.entry.map_or("Input", |idx| entries[idx].label()));
+                            for value in stage.position.into_array() {
+                                ui.label(format!("{value:.3}"));
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+        }
+        if let Some(n) = remove {
+            self.points.remove(n);
+        }
+        if ui.button("Clear probes").clicked() {
+            self.points.clear();
+        }
+    }
+
+    // `outer` takes the stages from the space of the stack to the world
+    pub fn draw(&self, overlay: &Overlay, entries: &[StackEntry], convention: Convention, outer: Mat4<f32>) {
+        let painter = &overlay.painter;
+        for (n, point) in self.points.iter().enumerate() {
+            let color = PROBE_COLORS[n % PROBE_COLORS.len()];
+            // stages that went off to infinity break the chain
+            let positions = stages(entries, convention, *point)
+                .into_iter()
+                .map(|stage| {
+                    let p = outer * stage.position;
+                    let screen = (p.w.abs() > f32::EPSILON).then(|| overlay.to_screen(Vec2::new(p.x, p.y) / p.w));
+                    (stage_label(&stage), screen)
+                })
+                .collect::<Vec<_>>();
+            for pair in positions.windows(2) {
+                if let (Some(a), Some(b)) = (pair[0].1, pair[1].1) {
+                    painter.arrow(a, b - a, Stroke::new(1.0, color.gamma_multiply(0.7)));
+                }
+            }
+            for (i, (label, screen)) in positions.iter().enumerate() {
+                let Some(screen) = screen else {
+                    continue;
+                };
+                if i == 0 {
+                    painter.circle_stroke(*screen, 4.0, Stroke::new(2.0, color));
+                } else {
+                    painter.circle_filled(*screen, 3.0, color);
+                }
+                painter.text(*screen + egui::vec2(5.0, -5.0), egui::Align2::LEFT_BOTTOM, label, egui::FontId::proportional(11.0), color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{linalg, stack::MatrixInteractionType};
+    use super::*;
+
+    fn assert_stages(stages: &[Stage], expected: &[(Option<usize>, [f32; 4])]) {
+        assert_eq!(stages.len(), expected.len());
+        for (stage, (entry, position)) in stages.iter().zip(expected) {
+            assert_eq!(stage.entry, *entry);
+            let error = (stage.position - Vec4::from(*position)).map(f32::abs).reduce_partial_max();
+            assert!(error < 1e-6, "stage after {entry:?} is at {:?} instead of {position:?}", stage.position);
+        }
+    }
+
+    #[test]
+    fn stages_follow_the_convention() {
+        let translate = StackEntry::custom(Mat4::translation_2d(Vec2::new(1.0, 0.0)));
+        let rotate = StackEntry::custom(Mat4::rotation_z(std::f32::consts::FRAC_PI_2));
+        let mut disabled = StackEntry::custom(Mat4::scaling_3d(5.0));
+        disabled.enabled = false;
+        let entries = [rotate, disabled, translate];
+        let point = Vec2::new(1.0, 0.0);
+
+        // applied from the bottom: translate, then rotate
+        let bottom_first = Convention::default();
+        assert_stages(
+            &stages(&entries, bottom_first, point),
+            &[(None, [1.0, 0.0, 0.0, 1.0]), (Some(2), [2.0, 0.0, 0.0, 1.0]), (Some(0), [0.0, 2.0, 0.0, 1.0])],
+        );
+        // applied from the top: rotate, then translate
+        let top_first = Convention { row_vectors: false, pre_multiply: true };
+        assert_stages(
+            &stages(&entries, top_first, point),
+            &[(None, [1.0, 0.0, 0.0, 1.0]), (Some(0), [0.0, 1.0, 0.0, 1.0]), (Some(2), [1.0, 1.0, 0.0, 1.0])],
+        );
+    }
+
+    #[test]
+    fn homography_stage_keeps_w() {
+        let homography = StackEntry {
+            matrix: linalg::embed_homography([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.5, 1.0]]),
+            ..StackEntry::new(MatrixInteractionType::Homography { source: [Vec2::zero(); 4], target: [Vec2::zero(); 4] })
+        };
+        let rotate = StackEntry::custom(Mat4::rotation_z(std::f32::consts::FRAC_PI_2));
+        let translate = StackEntry::custom(Mat4::translation_2d(Vec2::new(1.0, 0.0)));
+        let entries = [homography, rotate, translate];
+        // translate to (2, 0), rotate to (0, 2), and the homography divides by w = 0.5 y + 1 = 2
+        assert_stages(
+            &stages(&entries, Convention::default(), Vec2::new(1.0, 0.0)),
+            &[
+                (None, [1.0, 0.0, 0.0, 1.0]),
+                (Some(2), [2.0, 0.0, 0.0, 1.0]),
+                (Some(1), [0.0, 2.0, 0.0, 1.0]),
+                (Some(0), [0.0, 2.0, 0.0, 2.0]),
+            ],
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use std::sync::Arc;
+
+use vek::{Mat4, Vec2, Vec3};
+
+use super::{
+    linalg,
+    matrix_grid::matrix_value_grid,
+    shapes::Shape,
+    stack::{self, stack_product, Convention, StackEntry},
+};
+use crate::renderer::{Renderable, Renderer, Vertex};
+
+// slot 0 of the object buffer holds the selected object's transform for the point cloud, objects follow it
+pub const POINT_CLOUD_SLOT: u32 = 0;
+
+fn slot(i: usize) -> u32 {
+    i as u32 + 1
+}
+
+fn identity_stack() -> Vec<StackEntry> {
+    vec![StackEntry::custom(Mat4::identity())]
+}
+
+pub struct Model {
+    _renderable: Arc<Renderable>,
+    // copy of the model whose vertices are moved on the cpu when the stack is non-linear
+    _warped: Arc<Renderable>,
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub shape: Shape,
+    pub subdivisions: u32,
+    pub transform: Mat4<f32>,
+}
+
+impl Model {
+    // (re)build the renderables of the model in the given slot, replacing the previous ones
+    pub fn new(renderer: &mut Renderer, shape: Shape, subdivisions: u32, transform: Mat4<f32>, slot: u32) -> Self {
+        let (vertices, indices) = shape.mesh(subdivisions);
+        let _renderable = renderer.add_renderable(Self::name(slot), "Default".into(), &indices, &vertices, slot);
+        let _warped = renderer.add_renderable(Self::warped_name(slot), "Warped".into(), &indices, &vertices, slot);
+        Self {
+            _renderable,
+            _warped,
+            vertices,
+            indices,
+            shape,
+            subdivisions,
+            transform,
+        }
+    }
+
+    pub fn name(slot: u32) -> String {
+        format!("model_{slot}")
+    }
+
+    pub fn warped_name(slot: u32) -> String {
+        format!("warped_model_{slot}")
+    }
+}
+
+pub struct SceneObject {
+    pub name: String,
+    pub model: Model,
+    pub stack: Vec<StackEntry>,
+    pub parent: Option<usize>, // the node whose world transform is applied after the object's own stack
+    pub parented: bool, // the shared parent stack is applied after the stack of a top level object
+    pub visible: bool,
+}
+
+// an object dragged around the outliner to be given a new parent
+struct OutlinerDrag(usize);
+
+// what the outliner asks for, done once the renderer is free again
+pub enum OutlinerAction {
+    Select,
+    Add,
+    Duplicate,
+    Remove,
+}
+
+// a tree of models that each have their own stack, under a parent stack the top level objects can share
+pub struct Scene {
+    pub objects: Vec<SceneObject>,
+    pub parent: Vec<StackEntry>,
+    pub selected: usize,
+    pub editing_parent: bool, // the stack editor shows the parent stack instead of the selected object's
+    pub convention: Convention,
+    next_name: usize,
+}
+
+impl Scene {
+    pub fn new(renderer: &mut Renderer, shape: Shape, subdivisions: u32) -> Self {
+        let mut scene = Self {
+            objects: Vec::new(),
+            parent: identity_stack(),
+            selected: 0,
+            editing_parent: false,
+            convention: Convention::default(),
+            next_name: 1,
+        };
+        scene.add(renderer, shape, subdivisions);
+        scene
+    }
+
+    pub fn add(&mut self, renderer: &mut Renderer, shape: Shape, subdivisions: u32) {
+        let model = Model::new(renderer, shape, subdivisions, Mat4::identity(), slot(self.objects.len()));
+        self.objects.push(SceneObject {
+            name: format!("Object {}", self.next_name),
+            model,
+            stack: identity_stack(),
+            parent: None,
+            parented: true,
+            visible: true,
+        });
+        self.next_name += 1;
+        self.selected = self.objects.len() - 1;
+        self.editing_parent = false;
+    }
+
+    pub fn duplicate(&mut self, renderer: &mut Renderer, i: usize) {
+        let object = &self.objects[i];
+        let (shape, subdivisions, stack, parent, parented) =
+            (object.model.shape.clone(), object.model.subdivisions, object.stack.clone(), object.parent, object.parented);
+        let name = format!("{} copy", object.name);
+        self.add(renderer, shape, subdivisions);
+        let copy = self.objects.last_mut().unwrap();
+        copy.name = name;
+        copy.stack = stack;
+        copy.parent = parent;
+        copy.parented = parented;
+    }
+
+    // the children of the removed object go to its parent. the objects after it move down a slot,
+    // so their renderables are rebuilt
+    pub fn remove(&mut self, renderer: &mut Renderer, i: usize) {
+        if self.objects.len() <= 1 {
+            return;
+        }
+        let last = slot(self.objects.len() - 1);
+        renderer.remove_renderable(&Model::name(last));
+        renderer.remove_renderable(&Model::warped_name(last));
+        let removed = self.objects.remove(i);
+        for object in &mut self.objects {
+            if object.parent == Some(i) {
+                object.parent = removed.parent;
+                object.parented = removed.parented;
+            }
+            object.parent = object.parent.map(|p| if p > i { p - 1 } else { p });
+        }
+        for (j, object) in self.objects.iter_mut().enumerate().skip(i) {
+            let model = &object.model;
+            object.model = Model::new(renderer, model.shape.clone(), model.subdivisions, model.transform, slot(j));
+        }
+        self.selected = self.selected.min(self.objects.len() - 1);
+    }
+
+    // replace the model of the selected object
+    pub fn set_model(&mut self, renderer: &mut Renderer, shape: Shape, subdivisions: u32) {
+        let transform = self.selected().model.transform;
+        self.objects[self.selected].model = Model::new(renderer, shape, subdivisions, transform, slot(self.selected));
+    }
+
+    pub fn selected(&self) -> &SceneObject {
+        &self.objects[self.selected]
+    }
+
+    pub fn slot(&self, i: usize) -> u32 {
+        slot(i)
+    }
+
+    // the stack shown in the stack editor
+    pub fn stack(&self) -> &Vec<StackEntry> {
+        if self.editing_parent { &self.parent } else { &self.objects[self.selected].stack }
+    }
+
+    pub fn stack_mut(&mut self) -> &mut Vec<StackEntry> {
+        if self.editing_parent { &mut self.parent } else { &mut self.objects[self.selected].stack }
+    }
+
+    // what acts on a point after the edited stack on its way to the world: the world transform of the parent,
+    // which holds all the ancestors, or the shared stack for top-level objects that use it. nothing acts after
+    // the shared stack itself
+    pub fn outer_transform(&self) -> Mat4<f32> {
+        let object = self.selected();
+        match object.parent {
+            _ if self.editing_parent => Mat4::identity(),
+            Some(parent) => self.objects[parent].model.transform,
+            None if object.parented => stack_product(&self.parent, self.convention),
+            None => Mat4::identity(),
+        }
+    }
+
+    // everything that acts on a point after an entry of the edited stack
+    pub fn applied_after(&self, idx: usize) -> Mat4<f32> {
+        self.outer_transform() * stack::applied_after(self.stack(), idx, self.convention)
+    }
+
+    pub fn stacks_mut(&mut self) -> impl Iterator<Item = &mut Vec<StackEntry>> {
+        std::iter::once(&mut self.parent).chain(self.objects.iter_mut().map(|object| &mut object.stack))
+    }
+
+    fn children(&self, i: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.objects.len()).filter(move |j| self.objects[*j].parent == Some(i))
+    }
+
+    // the objects depth first, each after its parent, with their depth in the tree
+    pub fn tree(&self) -> Vec<(usize, usize)> {
+        fn visit(scene: &Scene, i: usize, depth: usize, tree: &mut Vec<(usize, usize)>) {
+            tree.push((i, depth));
+            for child in scene.children(i) {
+                visit(scene, child, depth + 1, tree);
+            }
+        }
+        let mut tree = Vec::with_capacity(self.objects.len());
+        for root in (0..self.objects.len()).filter(|i| self.objects[*i].parent.is_none()) {
+            visit(self, root, 0, &mut tree);
+        }
+        tree
+    }
+
+    fn is_ancestor(&self, ancestor: usize, i: usize) -> bool {
+        let mut parent = self.objects[i].parent;
+        while let Some(p) = parent {
+            if p == ancestor {
+                return true;
+            }
+            parent = self.objects[p].parent;
+        }
+        false
+    }
+
+    // move an object under another one, or to the top level. an object can't go below itself
+    pub fn reparent(&mut self, i: usize, parent: Option<usize>) {
+        if parent.is_some_and(|p| p == i || self.is_ancestor(i, p)) {
+            return;
+        }
+        self.objects[i].parent = parent;
+        if parent.is_none() {
+            self.objects[i].parented = true;
+        }
+    }
+
+    pub fn is_warped(&self, i: usize) -> bool {
+        let object = &self.objects[i];
+        stack::is_warped(&object.stack)
+            || match object.parent {
+                Some(p) => self.is_warped(p),
+                None => object.parented && stack::is_warped(&self.parent),
+            }
+    }
+
+    // send a point through the object's stack, then through the stacks of its ancestors
+    pub fn map_point(&self, i: usize, p: Vec2<f32>) -> Vec2<f32> {
+        let object = &self.objects[i];
+        let p = stack::apply_stack(&object.stack, self.convention, p);
+        match object.parent {
+            Some(parent) => self.map_point(parent, p),
+            None if object.parented => stack::apply_stack(&self.parent, self.convention, p),
+            None => p,
+        }
+    }
+
+    // move a point in space like the object's model: by its transform, or through a warped stack
+    // in the plane, keeping its z
+    pub fn move_point(&self, i: usize, p: Vec3<f32>) -> Vec3<f32> {
+        if self.is_warped(i) {
+            Vec3::from((self.map_point(i, p.xy()), p.z))
+        } else {
+            linalg::transform_point(&self.objects[i].model.transform, p)
+        }
+    }
+
+    // the world transform of an object is the one of its parent times its own stack
+    pub fn update_transforms(&mut self) {
+        let shared = stack_product(&self.parent, self.convention);
+        for (i, _) in self.tree() {
+            let object = &self.objects[i];
+            let parent = match object.parent {
+                Some(p) => self.objects[p].model.transform,
+                None if object.parented => shared,
+                None => Mat4::identity(),
+            };
+            let transform = parent * stack_product(&object.stack, self.convention);
+            // never hand NaNs or infinities to the gpu, keep the last valid transform instead
+            if linalg::is_finite(&transform) {
+                self.objects[i].model.transform = transform;
+            }
+        }
+    }
+
+    // the transform each slot of the object buffer is drawn with, warped models are moved on the cpu instead
+    pub fn gpu_transforms(&self) -> Vec<Mat4<f32>> {
+        let gpu_transform = |i| if self.is_warped(i) { Mat4::identity() } else { self.objects[i].model.transform };
+        std::iter::once(gpu_transform(self.selected)).chain((0..self.objects.len()).map(gpu_transform)).collect()
+    }
+
+    pub fn gui(&mut self, ui: &mut egui::Ui, show_full_matrix: bool) -> Option<OutlinerAction> {
+        let mut action = None;
+        let mut reparent = None;
+        // dropping an object on the shared stack moves it to the top level
+        let (shared, dropped) = ui.dnd_drop_zone::<OutlinerDrag, _>(egui::Frame::default(), |ui| {
+            ui.selectable_label(self.editing_parent, "Shared parent stack")
+        });
+        if shared.inner.clicked() && !self.editing_parent {
+            self.editing_parent = true;
+            action = Some(OutlinerAction::Select);
+        }
+        if let Some(dropped) = dropped {
+            reparent = Some((dropped.0, None));
+        }
+        ui.separator();
+        for (i, depth) in self.tree() {
+            let (_, dropped) = ui.dnd_drop_zone::<OutlinerDrag, ()>(egui::Frame::default(), |ui| {
+                ui.horizontal(|ui| {
+                    ui.add_space(16.0 * depth as f32);
+                    let object = &mut self.objects[i];
+                    ui.checkbox(&mut object.visible, "").on_hover_text("Visible");
+                    let selected = !self.editing_parent && self.selected == i;
+                    // drag an object onto another one to make it its child
+                    let label = ui.dnd_drag_source(egui::Id::new(("outliner_drag_and_drop", i)), OutlinerDrag(i), |ui| {
+                        ui.selectable_label(selected, &object.name)
+                    });
+                    if label.inner.clicked() && !selected {
+                        self.selected = i;
+                        self.editing_parent = false;
+                        action = Some(OutlinerAction::Select);
+                    }
+                    if object.parent.is_none() {
+                        ui.checkbox(&mut object.parented, "Shared").on_hover_text("Apply the shared parent stack");
+                    }
+                });
+            });
+            if let Some(dropped) = dropped {
+                reparent = Some((dropped.0, Some(i)));
+            }
+        }
+        if let Some((i, parent)) = reparent {
+            self.reparent(i, parent);
+        }
+        ui.separator();
+        if self.editing_parent {
+            ui.label("Shared parent");
+            let shared = stack_product(&self.parent, self.convention);
+            matrix_value_grid(ui, "outliner_shared", &shared, show_full_matrix, self.convention.row_vectors);
+        } else {
+            let (object, convention) = (&mut self.objects[self.selected], self.convention);
+            ui.text_edit_singleline(&mut object.name);
+            ui.horizontal(|ui| {
+                ui.vertical(|ui| {
+                    ui.label("Local");
+                    matrix_value_grid(ui, "outliner_local", &stack_product(&object.stack, convention), show_full_matrix, convention.row_vectors);
+                });
+                ui.separator();
+                ui.vertical(|ui| {
+                    ui.label("World");
+                    matrix_value_grid(ui, "outliner_world", &object.model.transform, show_full_matrix, convention.row_vectors);
+                });
+            });
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Add").clicked() {
+                action = Some(OutlinerAction::Add);
+            }
+            if ui.button("Duplicate").clicked() {
+                action = Some(OutlinerAction::Duplicate);
+            }
+            if ui.add_enabled(self.objects.len() > 1, egui::Button::new("Remove")).clicked() {
+                action = Some(OutlinerAction::Remove);
+            }
+        });
+        action
+    }
+}
@@ -0,0 +1,339 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use vek::{Mat4, Vec2, Vec3, Vec4};
+
+use super::{mesh, svg::Piece};
+use crate::renderer::Vertex;
+
+// keeps the vertex buffers of a model, and of its warped copy, far below the buffer size limit of wgpu
+const MAX_VERTICES: usize = 1 << 20;
+// the subdivisions of the coarse mesh used to estimate how many vertices a shape needs
+const ESTIMATE_SUBDIVISIONS: u32 = 8;
+
+// the shapes the stack can be applied to
+#[derive(Clone, PartialEq)]
+pub enum Shape {
+    Square,
+    LetterF,
+    Arrow,
+    Ellipse { rx: f32, ry: f32 },
+    Polygon { sides: u32 },
+    Smiley,
+    House,
+    Cube,
+    GridPatch { cells: u32 },
+    Drawn { points: Vec<Vec2<f32>> },
+    Svg { name: String, pieces: Vec<Piece> },
+    Mesh { name: String, vertices: Vec<Vertex>, indices: Vec<u32> },
+}
+
+impl Shape {
+    pub fn all() -> [Shape; 9] {
+        [
+            Shape::Square,
+            Shape::LetterF,
+            Shape::Arrow,
+            Shape::Ellipse { rx: 0.5, ry: 0.3 },
+            Shape::Polygon { sides: 6 },
+            Shape::Smiley,
+            Shape::House,
+            Shape::Cube,
+            Shape::GridPatch { cells: 4 },
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shape::Square => "Square",
+            Shape::LetterF => "Letter F",
+            Shape::Arrow => "Arrow",
+            Shape::Ellipse { .. } => "Circle / ellipse",
+            Shape::Polygon { .. } => "Regular polygon",
+            Shape::Smiley => "Smiley",
+            Shape::House => "House",
+            Shape::Cube => "Cube",
+            Shape::GridPatch { .. } => "Unit grid patch",
+            Shape::Drawn { .. } => "Drawn polygon",
+            Shape::Svg { .. } => "Imported SVG",
+            Shape::Mesh { .. } => "Imported mesh",
+        }
+    }
+
+    // shapes with faces that can hide each other
+    pub fn is_3d(&self) -> bool {
+        matches!(self, Shape::Cube | Shape::Mesh { .. })
+    }
+
+    // edit the parameters of the shape, gives the edited shape if they changed
+    pub fn gui(&self, ui: &mut egui::Ui) -> Option<Shape> {
+        match *self {
+            Shape::Ellipse { mut rx, mut ry } => {
+                let changed = ui
+                    .horizontal(|ui| {
+                        let changed = ui.add(egui::DragValue::new(&mut rx).speed(0.01).range(0.01..=10.0).prefix("rx: ")).changed();
+                        changed | ui.add(egui::DragValue::new(&mut ry).speed(0.01).range(0.01..=10.0).prefix("ry: ")).changed()
+                    })
+                    .inner;
+                changed.then_some(Shape::Ellipse { rx, ry })
+            }
+            Shape::Polygon { mut sides } => ui.add(egui::Slider::new(&mut sides, 3..=32).text("Sides")).changed().then_some(Shape::Polygon { sides }),
+            Shape::GridPatch { mut cells } => ui.add(egui::Slider::new(&mut cells, 1..=16).text("Cells")).changed().then_some(Shape::GridPatch { cells }),
+            _ => None,
+        }
+    }
+
+    // the triangles of the shape, pieces are split so every edge has about `subdivisions` segments per unit.
+    // shapes that would end up with more than MAX_VERTICES get fewer subdivisions
+    pub fn mesh(&self, subdivisions: u32) -> (Vec<Vertex>, Vec<u32>) {
+        // loaded meshes are used as they are
+        if let Shape::Mesh { vertices, indices, .. } = self {
+            return (vertices.clone(), indices.clone());
+        }
+        // the vertex count grows with the square of the subdivisions, so a coarse mesh tells how far they can go
+        let estimate = ESTIMATE_SUBDIVISIONS.min(subdivisions.max(1));
+        let count = self.build(estimate).0.len().max(1) as f32;
+        let limit = (estimate as f32 * (MAX_VERTICES as f32 / count).sqrt()) as u32;
+        let mut subdivisions = subdivisions.min(limit).max(1);
+        loop {
+            let mesh = self.build(subdivisions);
+            if mesh.0.len() <= MAX_VERTICES || subdivisions == 1 {
+                return mesh;
+            }
+            subdivisions /= 2;
+        }
+    }
+
+    fn build(&self, subdivisions: u32) -> (Vec<Vertex>, Vec<u32>) {
+        let mut mesh = MeshBuilder::new(subdivisions);
+        let p = |x: f32, y: f32| Vec3::new(x, y, 0.0);
+        match self {
+            Shape::Square => return mesh::subdivided_quad(subdivisions),
+            Shape::Mesh { vertices, indices, .. } => return (vertices.clone(), indices.clone()),
+            Shape::LetterF => {
+                mesh.quad(p(-0.3, -0.5), Vec3::unit_x() * 0.2, Vec3::unit_y(), 0.0);
+                mesh.quad(p(-0.1, 0.3), Vec3::unit_x() * 0.4, Vec3::unit_y() * 0.2, 0.0);
+                mesh.quad(p(-0.1, -0.05), Vec3::unit_x() * 0.25, Vec3::unit_y() * 0.2, 0.0);
+            }
+            Shape::Arrow => {
+                mesh.quad(p(-0.5, -0.1), Vec3::unit_x() * 0.6, Vec3::unit_y() * 0.2, 0.0);
+                mesh.triangle(p(0.1, -0.3), p(0.5, 0.0), p(0.1, 0.3), 0.0);
+            }
+            Shape::Ellipse { rx, ry } => mesh.fan(Vec3::zero(), &ellipse(Vec2::zero(), *rx, *ry, 0.0, 64), 0.0),
+            Shape::Polygon { sides } => mesh.fan(Vec3::zero(), &ellipse(Vec2::zero(), 0.5, 0.5, FRAC_PI_2, *sides), 0.0),
+            Shape::Smiley => {
+                mesh.fan(Vec3::zero(), &ellipse(Vec2::zero(), 0.5, 0.5, 0.0, 64), 0.0);
+                // features are drawn after the face, so they end up on top of it
+                for x in [-0.17, 0.17] {
+                    mesh.fan(p(x, 0.15), &ellipse(Vec2::new(x, 0.15), 0.06, 0.09, 0.0, 24), 0.7);
+                }
+                let outer = arc(0.33, 0.0, 16);
+                let inner = arc(0.25, 0.05, 16);
+                for i in 0..outer.len() - 1 {
+                    mesh.triangle(outer[i], outer[i + 1], inner[i], 0.7);
+                    mesh.triangle(outer[i + 1], inner[i + 1], inner[i], 0.7);
+                }
+            }
+            Shape::House => {
+                mesh.quad(p(-0.4, -0.5), Vec3::unit_x() * 0.8, Vec3::unit_y() * 0.6, 0.0);
+                mesh.triangle(p(-0.5, 0.1), p(0.5, 0.1), p(0.0, 0.5), 0.3);
+                mesh.quad(p(-0.25, -0.5), Vec3::unit_x() * 0.2, Vec3::unit_y() * 0.35, 0.6);
+                mesh.quad(p(0.1, -0.2), Vec3::unit_x() * 0.18, Vec3::unit_y() * 0.15, 0.6);
+            }
+            Shape::Cube => {
+                // each face shaded differently so rotations out of the plane can be followed
+                let faces = [
+                    (p(-0.5, -0.5) + Vec3::unit_z() * 0.5, Vec3::unit_x(), Vec3::unit_y(), 0.0),
+                    (p(-0.5, 0.5) - Vec3::unit_z() * 0.5, Vec3::unit_x(), -Vec3::unit_y(), 0.5),
+                    (p(0.5, -0.5) - Vec3::unit_z() * 0.5, Vec3::unit_z(), Vec3::unit_y(), 0.2),
+                    (p(-0.5, -0.5) - Vec3::unit_z() * 0.5, Vec3::unit_y(), Vec3::unit_z(), 0.4),
+                    (p(-0.5, 0.5) - Vec3::unit_z() * 0.5, Vec3::unit_z(), Vec3::unit_x(), 0.1),
+                    (p(-0.5, -0.5) - Vec3::unit_z() * 0.5, Vec3::unit_x(), Vec3::unit_z(), 0.3),
+                ];
+                for (corner, u, v, shade) in faces {
+                    mesh.quad(corner, u, v, shade);
+                }
+            }
+            // spans the unit square and beyond, so the images of the basis vectors can be read off
+            Shape::GridPatch { cells } => {
+                for j in 0..*cells {
+                    for i in 0..*cells {
+                        let shade = if (i + j) % 2 == 0 { 0.0 } else { 0.35 };
+                        mesh.quad(p(i as f32, j as f32), Vec3::unit_x(), Vec3::unit_y(), shade);
+                    }
+                }
+            }
+            // the drawing tool only hands over outlines that triangulate
+            Shape::Drawn { points } => {
+                for triangle in mesh::triangulate(points).unwrap_or_default().chunks(3) {
+                    let corner = |i: u32| p(points[i as usize].x, points[i as usize].y);
+                    mesh.triangle(corner(triangle[0]), corner(triangle[1]), corner(triangle[2]), 0.0);
+                }
+            }
+            Shape::Svg { pieces, .. } => {
+                // drawings come with many long thin triangles, so split them less to stay near n² triangles in total
+                let triangles = pieces.iter().map(|piece| piece.indices.len() / 3).sum::<usize>();
+                mesh.subdivisions = ((subdivisions as f32 / (triangles as f32).sqrt()).ceil() as u32).max(1);
+                for piece in pieces {
+                    mesh.color = piece.color;
+                    for triangle in piece.indices.chunks(3) {
+                        let corner = |i: u32| p(piece.points[i as usize].x, piece.points[i as usize].y);
+                        mesh.triangle(corner(triangle[0]), corner(triangle[1]), corner(triangle[2]), 0.0);
+                    }
+                }
+            }
+        }
+        mesh.finish()
+    }
+}
+
+// reorder the triangles of a 3d mesh back to front, as there is no depth buffer. +z points out of the screen
+pub fn sort_back_to_front(vertices: &[Vertex], indices: &[u32], transform: Mat4<f32>) -> Vec<u32> {
+    let depth = |i: u32| {
+        let p = transform * vertices[i as usize].position;
+        p.z / p.w
+    };
+    let mut triangles = indices
+        .chunks(3)
+        .map(|t| (depth(t[0]) + depth(t[1]) + depth(t[2]), [t[0], t[1], t[2]]))
+        .collect::<Vec<_>>();
+    triangles.sort_by(|a, b| a.0.total_cmp(&b.0));
+    triangles.into_iter().flat_map(|(_, t)| t).collect()
+}
+
+// points on an ellipse, starting at angle `start`
+fn ellipse(center: Vec2<f32>, rx: f32, ry: f32, start: f32, segments: u32) -> Vec<Vec3<f32>> {
+    (0..segments)
+        .map(|i| {
+            let t = start + i as f32 / segments as f32 * TAU;
+            Vec3::new(center.x + rx * t.cos(), center.y + ry * t.sin(), 0.0)
+        })
+        .collect()
+}
+
+// the lower half of a circle around the origin, moved up by `lift`
+fn arc(radius: f32, lift: f32, segments: u32) -> Vec<Vec3<f32>> {
+    (0..=segments)
+        .map(|i| {
+            let t = std::f32::consts::PI * (1.0 + i as f32 / segments as f32);
+            Vec3::new(radius * t.cos(), radius * t.sin() + lift, 0.0)
+        })
+        .collect()
+}
+
+struct MeshBuilder {
+    positions: Vec<Vec3<f32>>,
+    shades: Vec<f32>,
+    colors: Vec<Vec4<f32>>,
+    color: Vec4<f32>, // for the vertices pushed from now on, transparent keeps the corner colours
+    indices: Vec<u32>,
+    subdivisions: u32,
+}
+
+impl MeshBuilder {
+    fn new(subdivisions: u32) -> Self {
+        Self {
+            positions: Vec::new(),
+            shades: Vec::new(),
+            colors: Vec::new(),
+            color: Vec4::zero(),
+            indices: Vec::new(),
+            subdivisions: subdivisions.max(1),
+        }
+    }
+
+    fn segments(&self, length: f32) -> u32 {
+        ((length * self.subdivisions as f32).ceil() as u32).max(1)
+    }
+
+    fn push(&mut self, position: Vec3<f32>, shade: f32) -> u32 {
+        self.positions.push(position);
+        self.shades.push(shade);
+        self.colors.push(self.color);
+        self.positions.len() as u32 - 1
+    }
+
+    // the parallelogram corner + s*u + t*v for s, t in [0, 1]
+    fn quad(&mut self, corner: Vec3<f32>, u: Vec3<f32>, v: Vec3<f32>, shade: f32) {
+        let (nu, nv) = (self.segments(u.magnitude()), self.segments(v.magnitude()));
+        let first = self.positions.len() as u32;
+        for j in 0..=nv {
+            for i in 0..=nu {
+                self.push(corner + u * (i as f32 / nu as f32) + v * (j as f32 / nv as f32), shade);
+            }
+        }
+        let row = nu + 1;
+        for j in 0..nv {
+            for i in 0..nu {
+                let c = first + j * row + i;
+                self.indices.extend_from_slice(&[c, c + 1, c + row, c + 1, c + row + 1, c + row]);
+            }
+        }
+    }
+
+    // split into n² smaller triangles by walking rows from a towards c
+    fn triangle(&mut self, a: Vec3<f32>, b: Vec3<f32>, c: Vec3<f32>, shade: f32) {
+        let longest = (b - a).magnitude().max((c - b).magnitude()).max((a - c).magnitude());
+        let n = self.segments(longest);
+        // row j has n - j + 1 vertices
+        let mut starts = Vec::new();
+        for j in 0..=n {
+            starts.push(self.positions.len() as u32);
+            for i in 0..=n - j {
+                let (s, t) = (i as f32 / n as f32, j as f32 / n as f32);
+                self.push(a + (b - a) * s + (c - a) * t, shade);
+            }
+        }
+        for j in 0..n {
+            let (low, high) = (starts[j as usize], starts[j as usize + 1]);
+            for i in 0..n - j {
+                self.indices.extend_from_slice(&[low + i, low + i + 1, high + i]);
+                if i + 1 < n - j {
+                    self.indices.extend_from_slice(&[low + i + 1, high + i + 1, high + i]);
+                }
+            }
+        }
+    }
+
+    // triangles from the center to each edge of a closed outline
+    fn fan(&mut self, center: Vec3<f32>, outline: &[Vec3<f32>], shade: f32) {
+        for i in 0..outline.len() {
+            self.triangle(center, outline[i], outline[(i + 1) % outline.len()], shade);
+        }
+    }
+
+    // uv spans the bounding box in the plane so the corner colours follow the shape, and uv.z darkens
+    fn finish(self) -> (Vec<Vertex>, Vec<u32>) {
+        let min = self.positions.iter().fold(Vec2::broadcast(f32::INFINITY), |m, p| Vec2::partial_min(m, p.xy()));
+        let max = self.positions.iter().fold(Vec2::broadcast(f32::NEG_INFINITY), |m, p| Vec2::partial_max(m, p.xy()));
+        let size = (max - min).map(|s| s.max(f32::EPSILON));
+        let vertices = self
+            .positions
+            .iter()
+            .zip(&self.shades)
+            .zip(&self.colors)
+            .map(|((p, shade), color)| {
+                let uv = (p.xy() - min) / size;
+                Vertex::new(Vec4::new(p.x, p.y, p.z, 1.0), Vec4::new(uv.x, uv.y, *shade, 0.0)).with_color(*color)
+            })
+            .collect();
+        (vertices, self.indices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meshes_stay_below_vertex_limit() {
+        for shape in [Shape::GridPatch { cells: 16 }, Shape::Cube, Shape::Smiley, Shape::Polygon { sides: 32 }] {
+            let (vertices, indices) = shape.mesh(512);
+            assert!(vertices.len() <= MAX_VERTICES, "{} has {} vertices", shape.name(), vertices.len());
+            // the limit shouldn't cost more detail than needed
+            assert!(vertices.len() > MAX_VERTICES / 8, "{} has only {} vertices", shape.name(), vertices.len());
+            assert!(indices.iter().all(|i| (*i as usize) < vertices.len()));
+        }
+        // small meshes keep their subdivisions
+        assert_eq!(Shape::Square.mesh(48).0.len(), 49 * 49);
+    }
+}
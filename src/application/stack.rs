@@ -0,0 +1,493 @@
+use std::ops::Range;
+
+use egui::Color32;
+use vek::{Mat4, Vec2};
+
+use super::{complex, expr::WarpFunction, linalg::{self, Complex}};
+
+#[derive(Clone, PartialEq)]
+pub enum MatrixInteractionType {
+    CustomMatrix, // just a mat4
+    RotationMatrixZ(f32), // an angle to rotate about Z-axis
+    TranslationMatrix2D(Vec2<f32>), // a vec2 to translate along XY plane
+    ScaleMatrix2D(Vec2<f32>), // a vec2 to scale in XY
+    InverseOf { first: usize, last: usize, condition: f64 }, // inverse of the product of an inclusive range of entries
+    Exponential { generator: Mat4<f32>, t: f32, animate: Option<f32> }, // exp(tA), animate holds the direction t is moving in
+    FractionalPower { base: Mat4<f32>, s: f32 }, // A^s = exp(s log A)
+    Homography { source: [Vec2<f32>; 4], target: [Vec2<f32>; 4] }, // projective map taking four points to four points
+    ComplexMultiply(Complex), // z -> wz, a rotation-scaling
+    ComplexAdd(Complex), // z -> z + c
+    Reciprocal, // z -> 1/z
+    Mobius { a: Complex, b: Complex, c: Complex, d: Complex }, // z -> (az + b) / (cz + d)
+    Warp(WarpFunction), // (x, y) -> (f(x, y), g(x, y)) given as expressions
+    Group { len: usize }, // heads the len entries below it, nested groups counted with their contents
+}
+
+// how the stack is read: which side of the matrices vectors are on, and which side of the product so far each
+// entry further down the stack is multiplied on. matrices are always stored acting on column vectors
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct Convention {
+    pub row_vectors: bool,
+    pub pre_multiply: bool,
+}
+
+impl Convention {
+    // whether the entry at the bottom of the stack is the first to act on a point
+    pub fn bottom_first(&self) -> bool {
+        self.row_vectors == self.pre_multiply
+    }
+
+    pub fn help(&self) -> &'static str {
+        match (self.row_vectors, self.pre_multiply) {
+            (false, false) => "v' = M₀M₁…Mₙv, matrices are applied from bottom to top.",
+            (false, true) => "v' = Mₙ…M₁M₀v, matrices are applied from top to bottom.",
+            (true, false) => "v' = vM₀M₁…Mₙ, matrices are applied from top to bottom.",
+            (true, true) => "v' = vMₙ…M₁M₀, matrices are applied from bottom to top.",
+        }
+    }
+
+    pub fn gui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Vectors:");
+            ui.selectable_value(&mut self.row_vectors, false, "Column");
+            ui.selectable_value(&mut self.row_vectors, true, "Row");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Each entry down the stack multiplies:");
+            ui.selectable_value(&mut self.pre_multiply, false, "On the right");
+            ui.selectable_value(&mut self.pre_multiply, true, "On the left");
+        });
+        ui.label(self.help());
+    }
+}
+
+// a matrix of the stack, how it is edited, and how it is shown in the stack editor
+#[derive(Clone)]
+pub struct StackEntry {
+    pub matrix: Mat4<f32>,
+    pub interaction_type: MatrixInteractionType,
+    pub enabled: bool,
+    pub name: String, // shown instead of the label of the interaction type unless empty
+    pub color: Color32,
+    pub collapsed: bool, // only the title of the entry is shown, a collapsed group hides its entries too
+    pub note: Option<String>,
+    pub ifs_weight: f32, // how likely the entry is picked as a map of the iterated function system
+}
+
+impl StackEntry {
+    // the matrix of most entries is computed from their interaction type, so it starts as the identity
+    pub fn new(interaction_type: MatrixInteractionType) -> Self {
+        Self {
+            matrix: Mat4::identity(),
+            interaction_type,
+            enabled: true,
+            name: String::new(),
+            color: Color32::from_gray(140),
+            collapsed: false,
+            note: None,
+            ifs_weight: 1.0,
+        }
+    }
+
+    pub fn custom(matrix: Mat4<f32>) -> Self {
+        Self { matrix, ..Self::new(MatrixInteractionType::CustomMatrix) }
+    }
+
+    pub fn label(&self) -> &str {
+        if self.name.is_empty() { entry_label(&self.interaction_type) } else { &self.name }
+    }
+}
+
+pub fn entry_label(interaction_type: &MatrixInteractionType) -> &'static str {
+    match interaction_type {
+        MatrixInteractionType::CustomMatrix => "Custom matrix",
+        MatrixInteractionType::RotationMatrixZ(_) => "Rotation",
+        MatrixInteractionType::ScaleMatrix2D(_) => "Scale",
+        MatrixInteractionType::TranslationMatrix2D(_) => "Translation",
+        MatrixInteractionType::InverseOf { .. } => "Inverse of…",
+        MatrixInteractionType::Exponential { .. } => "Exponential exp(tA)",
+        MatrixInteractionType::FractionalPower { .. } => "Fractional power A^s",
+        MatrixInteractionType::Homography { .. } => "Homography",
+        MatrixInteractionType::ComplexMultiply(_) => "Multiply by a+bi",
+        MatrixInteractionType::ComplexAdd(_) => "Add c",
+        MatrixInteractionType::Reciprocal => "z → 1/z",
+        MatrixInteractionType::Mobius { .. } => "Möbius (az+b)/(cz+d)",
+        MatrixInteractionType::Warp(_) => "Warp f(x, y)",
+        MatrixInteractionType::Group { .. } => "Group",
+    }
+}
+
+// entries that can't be written as a matrix keep the identity as their matrix, and are evaluated point by point
+pub fn is_linear(interaction_type: &MatrixInteractionType) -> bool {
+    match interaction_type {
+        MatrixInteractionType::Reciprocal | MatrixInteractionType::Warp(_) => false,
+        MatrixInteractionType::Mobius { c, .. } => *c == Complex::ZERO,
+        _ => true,
+    }
+}
+
+// whether each entry takes part in the stack: enabled, not inside a disabled group, and not a group header
+pub fn active_entries(entries: &[StackEntry]) -> Vec<bool> {
+    let mut disabled_until = 0; // entries before this index are inside a disabled group
+    let mut active = Vec::with_capacity(entries.len());
+    for (idx, entry) in entries.iter().enumerate() {
+        let group = matches!(entry.interaction_type, MatrixInteractionType::Group { .. });
+        if group && !entry.enabled {
+            disabled_until = disabled_until.max(idx + span(entries, idx));
+        }
+        active.push(entry.enabled && !group && idx >= disabled_until);
+    }
+    active
+}
+
+// whether the stack bends the plane, so the model has to be moved vertex by vertex
+pub fn is_warped(entries: &[StackEntry]) -> bool {
+    entries.iter().zip(active_entries(entries)).any(|(entry, active)| active && !is_linear(&entry.interaction_type))
+}
+
+pub fn map_point(entry: &StackEntry, p: Vec2<f32>) -> Vec2<f32> {
+    let (mat, interaction_type) = (&entry.matrix, &entry.interaction_type);
+    match interaction_type {
+        MatrixInteractionType::Reciprocal => (Complex::ONE / Complex::from_point(p)).to_point(),
+        MatrixInteractionType::Mobius { a, b, c, d } if !is_linear(interaction_type) => complex::mobius(*a, *b, *c, *d, Complex::from_point(p)).to_point(),
+        MatrixInteractionType::Warp(f) => f.eval(p),
+        _ => linalg::transform_point_2d(mat, p),
+    }
+}
+
+// the active entries in the order they act on a point
+pub fn application_order(entries: &[StackEntry], convention: Convention) -> Vec<usize> {
+    let mut order: Vec<usize> = active_entries(entries).into_iter().enumerate().filter(|(_, active)| *active).map(|(idx, _)| idx).collect();
+    if convention.bottom_first() {
+        order.reverse();
+    }
+    order
+}
+
+// send a point through every active entry
+pub fn apply_stack(entries: &[StackEntry], convention: Convention, p: Vec2<f32>) -> Vec2<f32> {
+    application_order(entries, convention).into_iter().fold(p, |p, idx| map_point(&entries[idx], p))
+}
+
+// multiply the active entries together so the product acts like the stack on column vectors.
+// non-linear entries count as the identity
+pub fn stack_product(entries: &[StackEntry], convention: Convention) -> Mat4<f32> {
+    range_product(entries, 0..entries.len(), convention)
+}
+
+// the product of the active entries in part of the stack. whether an entry is active depends on the groups
+// around it, so that is worked out over the whole stack rather than the part
+pub fn range_product(entries: &[StackEntry], range: Range<usize>, convention: Convention) -> Mat4<f32> {
+    application_order(entries, convention)
+        .into_iter()
+        .filter(|idx| range.contains(idx))
+        .fold(Mat4::identity(), |acc, idx| entries[idx].matrix * acc)
+}
+
+// the product of the entries that act on a point after the given one
+pub fn applied_after(entries: &[StackEntry], idx: usize, convention: Convention) -> Mat4<f32> {
+    if convention.bottom_first() {
+        range_product(entries, 0..idx, convention)
+    } else {
+        range_product(entries, idx + 1..entries.len(), convention)
+    }
+}
+
+// keep the stack indices stored in entries pointing at the same entries after the stack changed
+pub fn remap_references(entries: &mut [StackEntry], map: impl Fn(usize) -> usize) {
+    for entry in entries.iter_mut() {
+        if let MatrixInteractionType::InverseOf { first, last, .. } = &mut entry.interaction_type {
+            *first = map(*first);
+            *last = map(*last);
+        }
+    }
+}
+
+// the number of entries an entry stands for: itself, and everything in it if it is a group
+pub fn span(entries: &[StackEntry], idx: usize) -> usize {
+    match entries[idx].interaction_type {
+        MatrixInteractionType::Group { len } => (1 + len).min(entries.len() - idx),
+        _ => 1,
+    }
+}
+
+// the groups an entry is in, outermost first
+fn enclosing_groups(entries: &[StackEntry], idx: usize) -> Vec<usize> {
+    (0..idx).filter(|h| idx < h + span(entries, *h)).collect()
+}
+
+fn resize_group(entries: &mut [StackEntry], idx: usize, change: impl Fn(usize) -> usize) {
+    if let MatrixInteractionType::Group { len } = &mut entries[idx].interaction_type {
+        *len = change(*len);
+    }
+}
+
+// how many groups each entry is nested in
+pub fn depths(entries: &[StackEntry]) -> Vec<usize> {
+    (0..entries.len()).map(|idx| enclosing_groups(entries, idx).len()).collect()
+}
+
+// the entries that aren't hidden inside a collapsed group
+pub fn visible_entries(entries: &[StackEntry]) -> Vec<usize> {
+    let mut hidden_until = 0;
+    let mut visible = Vec::with_capacity(entries.len());
+    for (idx, entry) in entries.iter().enumerate() {
+        if idx < hidden_until {
+            continue;
+        }
+        visible.push(idx);
+        if entry.collapsed && matches!(entry.interaction_type, MatrixInteractionType::Group { .. }) {
+            hidden_until = idx + span(entries, idx);
+        }
+    }
+    visible
+}
+
+// move an entry, with everything in it if it is a group, to the place of another entry and into the groups that
+// one is in. returns where each entry ended up, or None when a group would go inside itself
+pub fn move_entry(entries: &mut Vec<StackEntry>, from: usize, to: usize) -> Option<Vec<usize>> {
+    let len = entries.len();
+    let span = span(entries, from);
+    if (from..from + span).contains(&to) {
+        return None;
+    }
+    for group in enclosing_groups(entries, from) {
+        resize_group(entries, group, |len| len - span);
+    }
+    let block: Vec<StackEntry> = entries.drain(from..from + span).collect();
+    // moving down lands after the target, so dropping on a group header puts the entry first in that group
+    let (at, groups) = if to > from {
+        let target = to - span;
+        let mut groups = enclosing_groups(entries, target);
+        if matches!(entries[target].interaction_type, MatrixInteractionType::Group { .. }) {
+            groups.push(target);
+        }
+        (target + 1, groups)
+    } else {
+        (to, enclosing_groups(entries, to))
+    };
+    for group in groups {
+        resize_group(entries, group, |len| len + span);
+    }
+    entries.splice(at..at, block);
+    let map = |i: usize| {
+        if (from..from + span).contains(&i) {
+            return at + i - from;
+        }
+        let shifted = if i >= from + span { i - span } else { i };
+        if shifted >= at { shifted + span } else { shifted }
+    };
+    remap_references(entries, map);
+    Some((0..len).map(map).collect())
+}
+
+// remove an entry with everything in it. returns where each remaining entry ended up, or None when nothing
+// would be left
+pub fn remove_entry(entries: &mut Vec<StackEntry>, idx: usize) -> Option<Vec<Option<usize>>> {
+    let len = entries.len();
+    let span = span(entries, idx);
+    if span >= len {
+        return None;
+    }
+    for group in enclosing_groups(entries, idx) {
+        resize_group(entries, group, |len| len - span);
+    }
+    entries.drain(idx..idx + span);
+    remap_references(entries, |i| if i >= idx + span { i - span } else { i.min(idx) });
+    Some((0..len).map(|i| if i >= idx + span { Some(i - span) } else { (i < idx).then_some(i) }).collect())
+}
+
+// insert an entry after another one and everything in it, in the same groups. returns where each entry ended up
+pub fn insert_after(entries: &mut Vec<StackEntry>, idx: usize, entry: StackEntry) -> Vec<usize> {
+    let len = entries.len();
+    let at = idx + span(entries, idx);
+    for group in enclosing_groups(entries, idx) {
+        resize_group(entries, group, |len| len + 1);
+    }
+    entries.insert(at, entry);
+    let map = |i| if i >= at { i + 1 } else { i };
+    remap_references(entries, map);
+    (0..len).map(map).collect()
+}
+
+// copy an entry and everything in it right after itself. returns where each entry ended up
+pub fn duplicate_entry(entries: &mut Vec<StackEntry>, idx: usize) -> Vec<usize> {
+    let len = entries.len();
+    let span = span(entries, idx);
+    let at = idx + span;
+    // references inside the copy to the copied entries point at their copies
+    let mut block = entries[idx..at].to_vec();
+    remap_references(&mut block, |i| if i >= idx { i + span } else { i });
+    for group in enclosing_groups(entries, idx) {
+        resize_group(entries, group, |len| len + span);
+    }
+    remap_references(entries, |i| if i >= at { i + span } else { i });
+    entries.splice(at..at, block);
+    (0..len).map(|i| if i >= at { i + span } else { i }).collect()
+}
+
+// put an entry, with everything in it, in a new group of its own. returns where each entry ended up
+pub fn group_entry(entries: &mut Vec<StackEntry>, idx: usize) -> Vec<usize> {
+    let len = entries.len();
+    let span = span(entries, idx);
+    for group in enclosing_groups(entries, idx) {
+        resize_group(entries, group, |len| len + 1);
+    }
+    let group = StackEntry { name: "Group".into(), ..StackEntry::new(MatrixInteractionType::Group { len: span }) };
+    entries.insert(idx, group);
+    let map = |i| if i >= idx { i + 1 } else { i };
+    remap_references(entries, map);
+    (0..len).map(map).collect()
+}
+
+// take the entry after a group into it, if it is in the same groups as the group itself
+pub fn extend_group(entries: &mut [StackEntry], idx: usize) {
+    let next = idx + span(entries, idx);
+    if next < entries.len() && enclosing_groups(entries, next) == enclosing_groups(entries, idx) {
+        let span = span(entries, next);
+        resize_group(entries, idx, |len| len + span);
+    }
+}
+
+// remove a group but keep what is in it. returns where each entry ended up, or None when nothing would be left
+pub fn ungroup(entries: &mut Vec<StackEntry>, idx: usize) -> Option<Vec<Option<usize>>> {
+    let len = entries.len();
+    if len <= 1 {
+        return None;
+    }
+    for group in enclosing_groups(entries, idx) {
+        resize_group(entries, group, |len| len - 1);
+    }
+    entries.remove(idx);
+    remap_references(entries, |i| if i > idx { i - 1 } else { i });
+    Some((0..len).map(|i| if i > idx { Some(i - 1) } else { (i < idx).then_some(i) }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(x: f32) -> StackEntry {
+        StackEntry::new(MatrixInteractionType::TranslationMatrix2D(Vec2::new(x, 0.0)))
+    }
+
+    fn group(len: usize) -> StackEntry {
+        StackEntry::new(MatrixInteractionType::Group { len })
+    }
+
+    fn inverse_of(first: usize, last: usize) -> StackEntry {
+        StackEntry::new(MatrixInteractionType::InverseOf { first, last, condition: 1.0 })
+    }
+
+    // each entry in short: the x of a translation, G and the length of a group, I and the range of an inverse
+    fn layout(entries: &[StackEntry]) -> Vec<String> {
+        entries
+            .iter()
+            .map(|entry| match entry.interaction_type {
+                MatrixInteractionType::TranslationMatrix2D(v) => v.x.to_string(),
+                MatrixInteractionType::Group { len } => format!("G{len}"),
+                MatrixInteractionType::InverseOf { first, last, .. } => format!("I{first}-{last}"),
+                _ => "?".into(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn move_nested_group_up_and_down() {
+        let mut entries = vec![entry(0.0), group(4), entry(1.0), group(1), entry(2.0), entry(3.0), entry(4.0)];
+        // up and out of the outer group, which shrinks
+        let map = move_entry(&mut entries, 3, 0).unwrap();
+        assert_eq!(layout(&entries), ["G1", "2", "0", "G2", "1", "3", "4"]);
+        assert_eq!(map, [2, 3, 4, 0, 1, 5, 6]);
+        // down to the bottom, staying out of the group
+        let map = move_entry(&mut entries, 0, 6).unwrap();
+        assert_eq!(layout(&entries), ["0", "G2", "1", "3", "4", "G1", "2"]);
+        assert_eq!(map, [5, 6, 0, 1, 2, 3, 4]);
+        // up onto an entry of the outer group, which grows again
+        let map = move_entry(&mut entries, 5, 3).unwrap();
+        assert_eq!(layout(&entries), ["0", "G4", "1", "G1", "2", "3", "4"]);
+        assert_eq!(map, [0, 1, 2, 5, 6, 3, 4]);
+        // down onto the last entry of the outer group, which keeps it inside
+        let map = move_entry(&mut entries, 3, 5).unwrap();
+        assert_eq!(layout(&entries), ["0", "G4", "1", "3", "G1", "2", "4"]);
+        assert_eq!(map, [0, 1, 2, 4, 5, 3, 6]);
+    }
+
+    #[test]
+    fn move_group_into_itself() {
+        let mut entries = vec![group(2), entry(1.0), entry(2.0), entry(3.0)];
+        assert!(move_entry(&mut entries, 0, 2).is_none());
+        assert_eq!(layout(&entries), ["G2", "1", "2", "3"]);
+    }
+
+    #[test]
+    fn drop_onto_group_header() {
+        // moving down onto a header puts the entry first in the group
+        let mut entries = vec![entry(0.0), group(1), entry(1.0), entry(2.0)];
+        move_entry(&mut entries, 0, 1).unwrap();
+        assert_eq!(layout(&entries), ["G2", "0", "1", "2"]);
+        // moving up onto a header puts the entry right above the group
+        let mut entries = vec![group(1), entry(1.0), entry(2.0)];
+        move_entry(&mut entries, 2, 0).unwrap();
+        assert_eq!(layout(&entries), ["2", "G1", "1"]);
+    }
+
+    #[test]
+    fn move_keeps_references() {
+        let mut entries = vec![entry(0.0), entry(1.0), group(1), entry(2.0), inverse_of(0, 1)];
+        move_entry(&mut entries, 0, 3).unwrap();
+        assert_eq!(layout(&entries), ["1", "G2", "2", "0", "I3-0"]);
+    }
+
+    #[test]
+    fn remove_group_with_reference() {
+        let mut entries = vec![entry(0.0), group(2), entry(1.0), inverse_of(0, 0), entry(3.0), inverse_of(0, 4), inverse_of(2, 3)];
+        let map = remove_entry(&mut entries, 1).unwrap();
+        // references into the removed group point at whatever took its place
+        assert_eq!(layout(&entries), ["0", "3", "I0-1", "I1-1"]);
+        assert_eq!(map, [Some(0), None, None, None, Some(1), Some(2), Some(3)]);
+    }
+
+    #[test]
+    fn stack_never_empties() {
+        let mut entries = vec![entry(0.0)];
+        group_entry(&mut entries, 0);
+        assert_eq!(layout(&entries), ["G1", "0"]);
+        remove_entry(&mut entries, 1).unwrap();
+        assert_eq!(layout(&entries), ["G0"]);
+        assert!(ungroup(&mut entries, 0).is_none());
+        assert!(remove_entry(&mut entries, 0).is_none());
+        assert_eq!(layout(&entries), ["G0"]);
+        // nor does removing a group holding everything
+        let mut entries = vec![group(2), entry(1.0), entry(2.0)];
+        assert!(remove_entry(&mut entries, 0).is_none());
+        assert_eq!(layout(&entries), ["G2", "1", "2"]);
+    }
+
+    #[test]
+    fn remove_from_nested_group() {
+        let mut entries = vec![group(4), entry(1.0), group(2), entry(3.0), entry(4.0), entry(5.0)];
+        remove_entry(&mut entries, 3);
+        assert_eq!(layout(&entries), ["G3", "1", "G1", "4", "5"]);
+    }
+
+    #[test]
+    fn duplicate_group_with_inner_references() {
+        let mut entries = vec![group(4), group(2), entry(2.0), inverse_of(2, 2), entry(4.0), inverse_of(1, 3), inverse_of(4, 4)];
+        let map = duplicate_entry(&mut entries, 1);
+        // the copy refers to its own entries, the original and the rest of the stack stay as they were
+        assert_eq!(layout(&entries), ["G7", "G2", "2", "I2-2", "G2", "2", "I5-5", "4", "I1-3", "I7-7"]);
+        assert_eq!(map, [0, 1, 2, 3, 7, 8, 9]);
+    }
+
+    #[test]
+    fn group_and_ungroup() {
+        let mut entries = vec![group(2), entry(1.0), entry(2.0), inverse_of(1, 2)];
+        let map = group_entry(&mut entries, 2);
+        assert_eq!(layout(&entries), ["G3", "1", "G1", "2", "I1-3"]);
+        assert_eq!(map, [0, 1, 3, 4]);
+        let map = ungroup(&mut entries, 0).unwrap();
+        assert_eq!(layout(&entries), ["1", "G1", "2", "I0-2"]);
+        assert_eq!(map, [None, Some(0), Some(1), Some(2), Some(3)]);
+    }
+}
@@ -1,17 +1,43 @@
-use super::{camera::Camera, input::Input};
-use crate::renderer::{Renderable, Renderer, Vertex};
+use super::{
+    basis::ChangeOfBasis,
+    camera::Camera,
+    complex::{self, ComplexPlane},
+    drawing::PolygonTool,
+    files,
+    import::ModelImport,
+    dynamics::DynamicalSystem,
+    export,
+    expr::{self, WarpFunction},
+    fitting::TransformFit,
+    gizmo::{self, Gizmo},
+    ifs::{self, IteratedFunctionSystem},
+    input::Input,
+    linalg::{self, Complex},
+    matrix_grid::{matrix_drag_grid, matrix_value_grid},
+    overlay::Overlay,
+    points::{self, PointCloud},
+    probe::PointProbe,
+    scene::{Model, OutlinerAction, Scene, POINT_CLOUD_SLOT},
+    shapes::{self, Shape},
+    stack::{self, entry_label, Convention, MatrixInteractionType, StackEntry},
+    texture::{self, Image, ModelTexture},
+    viewport::{SplitView, Viewport},
+    warp::{self, WarpedGrid},
+};
+use crate::renderer::{object_slots, Renderer, View};
 use log::{debug, trace};
 use std::sync::Arc;
-use vek::{Mat4, Vec4, Vec3, Vec2};
+use vek::{Mat4, Vec3, Vec2};
 use wgpu::BufferUsages;
 use winit::{window::Window};
 
-#[derive(PartialEq)]
-enum MatrixInteractionType {
-    CustomMatrix, // just a mat4
-    RotationMatrixZ(f32), // an angle to rotate about Z-axis
-    TranslationMatrix2D(Vec2<f32>), // a vec2 to translate along XY plane
-    ScaleMatrix2D(Vec2<f32>), // a vec2 to scale in XY
+// what the gui asks for that has to wait until the renderer is done running it
+#[derive(Default)]
+struct GuiChanges {
+    model: Option<(Shape, u32)>,
+    texture: Option<Image>,
+    points: bool,
+    outliner: Option<OutlinerAction>,
 }
 
 pub struct ApplicationState {
@@ -19,47 +45,51 @@ pub struct ApplicationState {
     pub renderer: Renderer,
     pub input: Input,
 
-    matrix_stack: Vec<(Mat4<f32>, MatrixInteractionType, bool)>,
+    scene: Scene,
+    show_outliner: bool,
     show_full_matrix: bool,
-    model: Model,
+    export_precision: usize, // decimals of matrices copied as code
+    split: SplitView,
+    complex: ComplexPlane,
+    warped_grid: WarpedGrid,
+    show_jacobian: bool,
+    basis: ChangeOfBasis,
 
-    last_timestamp: instant::Instant,
-}
+    dynamics: DynamicalSystem,
+    ifs: IteratedFunctionSystem,
+    fit: TransformFit,
+    probe: PointProbe,
+    drawing: PolygonTool,
+    import: ModelImport,
+    texture: ModelTexture,
+    points: PointCloud,
+    gizmo: Gizmo,
+    selected_entry: Option<usize>,
 
-struct Model {
-    _renderable: Arc<Renderable>,
-    pub transform: Mat4<f32>,
+    last_timestamp: instant::Instant,
 }
 
 impl ApplicationState {
     pub async fn new(window: Arc<Window>) -> Self {
         let input = Input::new();
         let mut renderer = Renderer::new(window.clone()).await;
-        let vertices = [
-            Vertex::new(
-                Vec4::new(-0.5, -0.5, 0.0, 1.0),
-                Vec4::new(0.0, 0.0, 0.0, 0.0),
-            ),
-            Vertex::new(
-                Vec4::new(0.5, -0.5, 0.0, 1.0),
-                Vec4::new(1.0, 0.0, 0.0, 0.0),
-            ),
-            Vertex::new(
-                Vec4::new(-0.5, 0.5, 0.0, 1.0),
-                Vec4::new(0.0, 1.0, 0.0, 0.0),
-            ),
-            Vertex::new(
-                Vec4::new(0.5, 0.5, 0.0, 1.0),
-                Vec4::new(1.0, 1.0, 0.0, 0.0),
-            ),
-        ];
-        let indices = [0, 1, 2, 1, 3, 2];
-        let _renderable = renderer.add_renderable("default_rect".into(), "Default".into(), &indices, &vertices);
-        let model = Model {
-            _renderable, 
-            transform: Mat4::identity()
-        };
-        let matrix_stack = vec![(Mat4::identity(),MatrixInteractionType::CustomMatrix, true)];
+        // a file given on the command line replaces the default shape, or is shown as a point cloud
+        let mut import = ModelImport::new();
+        let mut points = PointCloud::new();
+        #[cfg(not(target_arch = "wasm32"))]
+        let shape = std::env::args().nth(1).and_then(|path| {
+            if path.to_lowercase().ends_with(".csv") {
+                points.load(files::read_path(&path));
+                points.enabled = true;
+                return None;
+            }
+            let shape = import.load(files::read_path(&path));
+            import.enabled = shape.is_none();
+            shape
+        });
+        #[cfg(target_arch = "wasm32")]
+        let shape = None;
+        let scene = Scene::new(&mut renderer, shape.unwrap_or(Shape::Square), 48);
         let camera = Camera::new();
         renderer.add_global_buffer(
             "camera".into(),
@@ -67,21 +97,52 @@ impl ApplicationState {
             bytemuck::cast_slice(camera.get_matrix(renderer.aspect()).as_col_slice()),
             BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         );
-        renderer.add_global_buffer(
-            "transform".into(),
-            1,
-            bytemuck::cast_slice(model.transform.as_col_slice()),
-            BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-        );
+        // every renderable is drawn with the transform in the slot of its object
+        renderer.add_object_buffer("transform".into(), 1, &transform_slots(&scene.gpu_transforms()));
         renderer.add_global_buffer(
             "camera_pan_zoom".into(),
             2,
             bytemuck::cast_slice(&[camera.pan_and_zoom_data(renderer.aspect())]),
             BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         );
+        let ifs = IteratedFunctionSystem::new();
+        renderer.add_global_buffer(
+            "ifs".into(),
+            3,
+            bytemuck::cast_slice(&[ifs.data(std::iter::empty())]),
+            BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        );
+        let basis = ChangeOfBasis::new();
+        renderer.add_global_buffer(
+            "basis".into(),
+            4,
+            bytemuck::cast_slice(&[basis.data()]),
+            BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        );
+        let mut texture = ModelTexture::new();
+        // a model that brings its own texture is shown with it
+        let image = match import.take_texture() {
+            Some((name, image)) => {
+                texture.use_model_texture(name);
+                image
+            }
+            None => texture::default_texture(),
+        };
+        renderer.add_global_buffer(
+            "texturing".into(),
+            5,
+            bytemuck::cast_slice(&texture.data()),
+            BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        );
+        renderer.add_global_texture("model_texture".into(), 6, image.width, image.height, &image.rgba);
         // now that the renderer knows about this buffer... we can enable its grid pass
         renderer.add_grid_pass();
         renderer.add_pass("Default".into());
+        renderer.add_pass("Warped".into());
+        renderer.add_ifs_pass();
+        renderer.add_points_pass("Points".into());
+        renderer.add_points_pass("Warped points".into());
+        add_point_cloud(&mut renderer, &points);
 
         debug!("Application state initialized");
         Self {
@@ -89,9 +150,26 @@ impl ApplicationState {
             renderer,
             camera,
 
-            matrix_stack,
+            scene,
+            show_outliner: false,
             show_full_matrix: false,
-            model,
+            export_precision: 3,
+            split: SplitView::new(),
+            complex: ComplexPlane::new(),
+            warped_grid: WarpedGrid::new(),
+            show_jacobian: false,
+            basis,
+
+            dynamics: DynamicalSystem::new(),
+            ifs,
+            fit: TransformFit::new(),
+            probe: PointProbe::new(),
+            drawing: PolygonTool::new(),
+            import,
+            texture,
+            points,
+            gizmo: Gizmo::new(),
+            selected_entry: None,
 
             last_timestamp: instant::Instant::now(),
         }
@@ -109,11 +187,12 @@ impl ApplicationState {
             .gui_renderer
             .input_state
             .take_egui_input(&self.renderer.window);
-        let egui_output = self
-            .renderer
-            .gui_renderer
-            .input_state
-            .egui_ctx()
+        let window_size = Vec2::new(self.renderer.size.width, self.renderer.size.height).as_();
+        let (before_view, after_view) = self.split.layout(window_size);
+        // the renderer is busy running the gui, so the model is rebuilt afterwards
+        let mut changes = GuiChanges::default();
+        let egui_ctx = self.renderer.gui_renderer.input_state.egui_ctx().clone();
+        let egui_output = egui_ctx
             .run(raw_input, |ctx| {
                 #[cfg(debug_assertions)] // doesn't exclusively mean we're building in debug mode,
                                          // but close enough
@@ -128,61 +207,110 @@ impl ApplicationState {
                     ui.label(format!("grid_ceil: {}", grid_level.ceil()));
                     ui.label(format!("t: {}", grid_level - grid_level.floor()));
                 });
+                if self.show_outliner {
+                    self.outliner_window(ctx, &mut changes);
+                }
                 egui::Window::new("Controls")
                 .max_height(self.renderer.size.height as f32 - 128.0)
                 .default_width(128.0)
                 .show(ctx, |ui| {
                     ui.heading("Transform stack");
+                    ui.label(if self.scene.editing_parent { "Shared parent".to_string() } else { self.scene.selected().name.clone() });
                     
-                    let mut remove_index = None;
-                    
+                    let mut entry_action = None;
+                    let mut clicked_entry = None;
+
                     let mut from : Option<usize> = None;
                     let mut to : Option<usize> = None;
-                    
+
+                    // each entry next to its representation in the custom basis
+                    let basis = self.basis.enabled.then(|| (self.basis.matrix(), self.basis.inverse()));
+                    // with row vectors every matrix is shown transposed, and so is the change of basis
+                    let convention = self.scene.convention;
+                    let transposed = convention.row_vectors;
+                    let conjugate_label = if transposed { "BMB⁻¹" } else { "B⁻¹MB" };
                     let row_height = 5.5 + (self.show_full_matrix as u8 as f32) * ui.text_style_height(&egui::TextStyle::Body);
-                    let total_rows = self.matrix_stack.len();
-                    egui::ScrollArea::vertical().show_rows(ui, row_height, total_rows,|ui, row_range| {
-                        for (idx, (mat, interaction_type, enabled)) in self.matrix_stack[row_range].iter_mut().enumerate() {
-                            let frame = egui::Frame::default().inner_margin(1.0);
+                    // entries inside collapsed groups get no row, the others are indented by how deeply they are nested
+                    let stack = self.scene.stack();
+                    let visible = stack::visible_entries(stack);
+                    let depths = stack::depths(stack);
+                    let group_products: Vec<_> = (0..stack.len())
+                        .map(|idx| stack::range_product(stack, idx + 1..idx + stack::span(stack, idx), convention))
+                        .collect();
+                    let total_rows = stack.len();
+                    egui::ScrollArea::vertical().show_rows(ui, row_height, visible.len(),|ui, row_range| {
+                        let stack = self.scene.stack_mut();
+                        for &idx in &visible[row_range] {
+                            let title = stack[idx].label().to_string();
+                            let StackEntry { matrix: mat, interaction_type, enabled, name, color, collapsed, note, .. } = &mut stack[idx];
+                            let selected = self.selected_entry == Some(idx);
+                            let indent = egui::Margin { left: (12 * depths[idx]).min(120) as i8, ..egui::Margin::ZERO };
+                            let frame = egui::Frame::default().inner_margin(1.0).outer_margin(indent);
                             // specify zone for drag n drop
                             let (_, _dropped_payload) = ui.dnd_drop_zone::<usize, ()>(frame, |ui| {
                                 let item_id = egui::Id::new(("matrix_stack_drag_and_drop", idx));
                                 // track dragging events
-                                let response = ui.group(|ui| {
+                                let mut group = egui::Frame::group(ui.style());
+                                if selected {
+                                    group = group.stroke(ui.visuals().selection.stroke);
+                                }
+                                let response = group.show(ui, |ui| {
                                     // make list elements draggable by their names
                                     ui.horizontal(|ui| {
+                                        if ui.small_button(if *collapsed { "⏵" } else { "⏷" }).clicked() {
+                                            *collapsed = !*collapsed;
+                                        }
+                                        ui.color_edit_button_srgba(color);
                                         ui.dnd_drag_source(item_id, idx, |ui| {
-                                            ui.label(
-                                                match interaction_type {
-                                                    MatrixInteractionType::CustomMatrix => "Custom matrix",
-                                                    MatrixInteractionType::RotationMatrixZ(_) => "Rotation",
-                                                    MatrixInteractionType::ScaleMatrix2D(_) => "Scale",
-                                                    MatrixInteractionType::TranslationMatrix2D(_) => "Translation",
-                                            });
+                                            let label = ui.add(egui::Label::new(title).sense(egui::Sense::click()));
+                                            // clicking an entry selects it for editing on the canvas
+                                            if label.clicked() {
+                                                clicked_entry = Some(idx);
+                                            }
+                                            label.context_menu(|ui| {
+                                                    if ui.button("Insert inverse after").clicked() {
+                                                        entry_action = Some((idx, EntryAction::InsertInverse));
+                                                        ui.close();
+                                                    }
+                                                    if ui.button("Duplicate").clicked() {
+                                                        entry_action = Some((idx, EntryAction::Duplicate));
+                                                        ui.close();
+                                                    }
+                                                    if ui.button("Group").clicked() {
+                                                        entry_action = Some((idx, EntryAction::Group));
+                                                        ui.close();
+                                                    }
+                                                    ui.menu_button("Copy as…", |ui| {
+                                                        let is_group = matches!(interaction_type, MatrixInteractionType::Group { .. });
+                                                        let matrix = if is_group { group_products[idx] } else { *mat };
+                                                        export::copy_menu(ui, &matrix, convention, &mut self.export_precision);
+                                                    });
+                                                    if ui.button(if note.is_some() { "Remove note" } else { "Add note" }).clicked() {
+                                                        *note = if note.is_some() { None } else { Some(String::new()) };
+                                                        ui.close();
+                                                    }
+                                                    if matches!(interaction_type, MatrixInteractionType::Group { .. }) {
+                                                        if ui.button("Take in next entry").clicked() {
+                                                            entry_action = Some((idx, EntryAction::ExtendGroup));
+                                                            ui.close();
+                                                        }
+                                                        if ui.button("Ungroup").clicked() {
+                                                            entry_action = Some((idx, EntryAction::Ungroup));
+                                                            ui.close();
+                                                        }
+                                                    }
+                                                });
                                         });
                                         if idx > 0 {
                                             ui.checkbox(enabled, "Active?");
                                         }
                                     });
                                     // matrix list entry
-                                    ui.group(|ui| {
-                                        let values = mat.as_mut_col_slice();
+                                    let mut body = |ui: &mut egui::Ui| ui.group(|ui| {
+                                        ui.add(egui::TextEdit::singleline(name).hint_text(entry_label(interaction_type)));
                                         // add each element of the matrix as a dragvalue
                                         if *interaction_type == MatrixInteractionType::CustomMatrix {
-                                            egui::Grid::new(&format!("Matrix_{idx}")).show(ui, |ui| {
-                                                for y in 0..4usize {
-                                                    if !self.show_full_matrix && y == 2 {
-                                                        continue;
-                                                    }
-                                                    for x in 0..4usize {
-                                                        if !self.show_full_matrix && x == 2 {
-                                                            continue;
-                                                        }
-                                                        ui.add(egui::DragValue::new(&mut values[x*4 + y]).speed(0.01));
-                                                    }
-                                                    ui.end_row();
-                                                }
-                                            });
+                                            matrix_drag_grid(ui, format!("Matrix_{idx}"), mat, self.show_full_matrix, transposed);
                                         }
                                         // add interaction for preset matrix
                                         match interaction_type {
@@ -201,15 +329,187 @@ impl ApplicationState {
                                                 ui.add(egui::DragValue::new(&mut scale.y).speed(0.01).prefix("y: "));
                                                 *mat = Mat4::scaling_3d(Vec3::new(scale.x, scale.y, 1.0));
                                             },
+                                            MatrixInteractionType::InverseOf { first, last, condition } => {
+                                                // the matrix itself is resolved in update, since it depends on other entries
+                                                let max_index = total_rows.saturating_sub(1);
+                                                ui.horizontal(|ui| {
+                                                    ui.label("Entries");
+                                                    ui.add(egui::DragValue::new(first).range(0..=max_index));
+                                                    ui.label("to");
+                                                    ui.add(egui::DragValue::new(last).range(0..=max_index));
+                                                });
+                                                if (*first.min(last)..=*first.max(last)).contains(&idx) {
+                                                    ui.colored_label(ui.visuals().warn_fg_color, "An entry can't be the inverse of itself");
+                                                } else if condition.is_infinite() {
+                                                    ui.colored_label(ui.visuals().warn_fg_color, "Singular matrix, no inverse (condition number ∞)");
+                                                } else if *condition > linalg::ILL_CONDITIONED {
+                                                    ui.colored_label(ui.visuals().warn_fg_color, format!("Ill-conditioned matrix (condition number {condition:.3e})"));
+                                                } else {
+                                                    ui.label(format!("Condition number: {condition:.3}"));
+                                                }
+                                                matrix_value_grid(ui, format!("Matrix_values_{idx}"), mat, self.show_full_matrix, transposed);
+                                            },
+                                            MatrixInteractionType::Exponential { generator, t, animate } => {
+                                                ui.label("Generator A:");
+                                                matrix_drag_grid(ui, format!("Generator_{idx}"), generator, self.show_full_matrix, transposed);
+                                                ui.horizontal(|ui| {
+                                                    ui.add(egui::DragValue::new(t).speed(0.01).prefix("t: "));
+                                                    let mut animating = animate.is_some();
+                                                    if ui.checkbox(&mut animating, "Animate").changed() {
+                                                        *animate = animating.then_some(1.0);
+                                                    }
+                                                });
+                                                *mat = linalg::exp(&(*generator * *t));
+                                            },
+                                            MatrixInteractionType::FractionalPower { base, s } => {
+                                                ui.label("Base A:");
+                                                matrix_drag_grid(ui, format!("Base_{idx}"), base, self.show_full_matrix, transposed);
+                                                ui.add(egui::DragValue::new(s).speed(0.01).prefix("s: "));
+                                                let eigenvalues = linalg::eigenvalues(base).map(|l| l.to_string()).join(", ");
+                                                ui.label(format!("Eigenvalues: {eigenvalues}"));
+                                                match linalg::power(base, *s) {
+                                                    Ok(power) => *mat = power,
+                                                    Err(e) => {
+                                                        ui.colored_label(ui.visuals().warn_fg_color, e.to_string());
+                                                        *mat = Mat4::identity();
+                                                    }
+                                                }
+                                            },
+                                            MatrixInteractionType::Homography { source, target } => {
+                                                ui.label("Drag the points on the canvas, or edit them here:");
+                                                egui::Grid::new(format!("Homography_{idx}")).show(ui, |ui| {
+                                                    ui.label("");
+                                                    ui.colored_label(gizmo::SOURCE_COLOR, "Source");
+                                                    ui.colored_label(gizmo::TARGET_COLOR, "Target");
+                                                    ui.end_row();
+                                                    for n in 0..4 {
+                                                        ui.label(format!("{}", n + 1));
+                                                        for p in [&mut source[n], &mut target[n]] {
+                                                            ui.horizontal(|ui| {
+                                                                ui.add(egui::DragValue::new(&mut p.x).speed(0.01));
+                                                                ui.add(egui::DragValue::new(&mut p.y).speed(0.01));
+                                                            });
+                                                        }
+                                                        ui.end_row();
+                                                    }
+                                                });
+                                                match linalg::homography(source, target) {
+                                                    Some(h) => {
+                                                        *mat = linalg::embed_homography(h);
+                                                        // w changing sign inside the quad means part of it is sent through infinity
+                                                        let w = source.map(|p| h[2][0] * p.x as f64 + h[2][1] * p.y as f64 + h[2][2]);
+                                                        if w.iter().any(|w| *w <= 0.0) {
+                                                            ui.colored_label(ui.visuals().warn_fg_color, "The quad folds over itself, part of the plane is sent to infinity");
+                                                        }
+                                                    }
+                                                    None => {
+                                                        ui.colored_label(ui.visuals().warn_fg_color, "Three of the points are collinear, no homography exists");
+                                                        *mat = Mat4::identity();
+                                                    }
+                                                }
+                                                matrix_value_grid(ui, format!("Matrix_values_{idx}"), mat, self.show_full_matrix, transposed);
+                                            },
+                                            MatrixInteractionType::ComplexMultiply(w) => {
+                                                complex::complex_drag(ui, "w:", w);
+                                                ui.label(format!("Rotates by {:.1}° and scales by {:.3}", w.im.atan2(w.re).to_degrees(), w.abs()));
+                                                *mat = complex::multiplication_matrix(*w);
+                                            },
+                                            MatrixInteractionType::ComplexAdd(c) => {
+                                                complex::complex_drag(ui, "c:", c);
+                                                *mat = Mat4::translation_2d(c.to_point());
+                                            },
+                                            MatrixInteractionType::Reciprocal => {
+                                                ui.label("Inversion in the unit circle followed by conjugation, 0 and ∞ swap places");
+                                                *mat = Mat4::identity();
+                                            },
+                                            MatrixInteractionType::Mobius { a, b, c, d } => {
+                                                for (label, z) in [("a:", &mut *a), ("b:", &mut *b), ("c:", &mut *c), ("d:", &mut *d)] {
+                                                    complex::complex_drag(ui, label, z);
+                                                }
+                                                if complex::is_degenerate(*a, *b, *c, *d) {
+                                                    ui.colored_label(ui.visuals().warn_fg_color, "ad - bc = 0, the map is constant");
+                                                    *mat = Mat4::identity();
+                                                } else if *c == Complex::ZERO {
+                                                    // without the pole this is just z -> (a/d)z + b/d
+                                                    *mat = Mat4::<f32>::translation_2d((*b / *d).to_point()) * complex::multiplication_matrix(*a / *d);
+                                                } else {
+                                                    ui.label(format!("Pole at {}", Complex::ZERO - *d / *c));
+                                                    *mat = Mat4::identity();
+                                                }
+                                            },
+                                            MatrixInteractionType::Group { len } => {
+                                                ui.label(format!("{len} entries"));
+                                                // the group acts as the product of its entries, its own matrix stays the identity
+                                                matrix_value_grid(ui, format!("Group_{idx}"), &group_products[idx], self.show_full_matrix, transposed);
+                                                *mat = Mat4::identity();
+                                            },
+                                            MatrixInteractionType::Warp(f) => {
+                                                let mut changed = false;
+                                                egui::Grid::new(format!("Warp_{idx}")).show(ui, |ui| {
+                                                    ui.label("x' =");
+                                                    changed |= ui.text_edit_singleline(&mut f.x).changed();
+                                                    ui.end_row();
+                                                    ui.label("y' =");
+                                                    changed |= ui.text_edit_singleline(&mut f.y).changed();
+                                                    ui.end_row();
+                                                });
+                                                ui.menu_button("Presets", |ui| {
+                                                    for (name, x, y) in expr::presets() {
+                                                        if ui.button(name).clicked() {
+                                                            (f.x, f.y) = (x.into(), y.into());
+                                                            changed = true;
+                                                            ui.close();
+                                                        }
+                                                    }
+                                                });
+                                                if changed {
+                                                    f.reparse();
+                                                }
+                                                if let Some(error) = f.error() {
+                                                    ui.colored_label(ui.visuals().warn_fg_color, error);
+                                                }
+                                                *mat = Mat4::identity();
+                                            },
                                             _ => {},
                                         }
+                                        if let Some((basis, inverse)) = basis {
+                                            ui.separator();
+                                            ui.horizontal(|ui| {
+                                                ui.vertical(|ui| {
+                                                    ui.label("M");
+                                                    matrix_value_grid(ui, format!("Matrix_standard_{idx}"), mat, self.show_full_matrix, transposed);
+                                                });
+                                                ui.separator();
+                                                ui.vertical(|ui| {
+                                                    ui.label(conjugate_label);
+                                                    match inverse {
+                                                        Some(inverse) => {
+                                                            let conjugated = inverse * *mat * basis;
+                                                            matrix_value_grid(ui, format!("Matrix_basis_{idx}"), &conjugated, self.show_full_matrix, transposed)
+                                                        }
+                                                        None => { ui.label("Degenerate basis"); },
+                                                    }
+                                                });
+                                            });
+                                        }
+                                        if let Some(note) = note {
+                                            ui.add(egui::TextEdit::multiline(note).hint_text("Note").desired_rows(2));
+                                        }
                                         ui.allocate_space(ui.available_size());
                                     });
+                                    // a collapsed entry still runs its editor out of sight, which keeps its matrix up to date
+                                    if *collapsed {
+                                        body(&mut ui.new_child(egui::UiBuilder::new().invisible()));
+                                    } else {
+                                        body(ui);
+                                    }
                                 }).response;
                                 // tag matrix for deletion
                                 if idx > 0 {
-                                    if ui.button("Remove matrix").clicked() {
-                                        remove_index = Some(idx);
+                                    let is_group = matches!(interaction_type, MatrixInteractionType::Group { .. });
+                                    let remove_label = if is_group { "Remove group" } else { "Remove matrix" };
+                                    if ui.button(remove_label).clicked() {
+                                        entry_action = Some((idx, EntryAction::Remove));
                                     }
                                 }
                                 // detect dragndrop
@@ -224,17 +524,42 @@ impl ApplicationState {
                             });
                         } // end for
                     });
+                    if let Some(idx) = clicked_entry {
+                        self.selected_entry = if self.selected_entry == Some(idx) { None } else { Some(idx) };
+                    }
                     
-                    // rearrange matrices
-                    if let (Some(from), Some(mut to)) = (from, to) {
-                        let item = self.matrix_stack.remove(from);
-                        to = to.min(self.matrix_stack.len());
-                        self.matrix_stack.insert(to, item);
+                    // rearrange matrices, a group moves with everything in it
+                    if let (Some(from), Some(to)) = (from, to)
+                        && let Some(map) = stack::move_entry(self.scene.stack_mut(), from, to)
+                    {
+                        self.selected_entry = self.selected_entry.and_then(|i| map.get(i).copied());
                     }
 
-                    // remove matrix tagged for removal
-                    if let Some(idx) = remove_index {
-                        self.matrix_stack.remove(idx);
+                    if let Some((idx, action)) = entry_action {
+                        let stack = self.scene.stack_mut();
+                        // the stack never ends up empty, actions that would empty it do nothing
+                        let map = match action {
+                            EntryAction::Remove => stack::remove_entry(stack, idx),
+                            EntryAction::InsertInverse => {
+                                let last = idx + stack::span(stack, idx) - 1;
+                                let inverse = StackEntry::new(MatrixInteractionType::InverseOf { first: idx, last, condition: 1.0 });
+                                Some(stack::insert_after(stack, idx, inverse).into_iter().map(Some).collect())
+                            }
+                            EntryAction::Duplicate => Some(stack::duplicate_entry(stack, idx).into_iter().map(Some).collect()),
+                            EntryAction::Group => Some(stack::group_entry(stack, idx).into_iter().map(Some).collect()),
+                            EntryAction::ExtendGroup => {
+                                stack::extend_group(stack, idx);
+                                Some((0..stack.len()).map(Some).collect())
+                            }
+                            EntryAction::Ungroup => stack::ungroup(stack, idx),
+                        };
+                        let map: Vec<Option<usize>> = map.unwrap_or_else(|| (0..stack.len()).map(Some).collect());
+                        self.selected_entry = match action {
+                            // the new entry is selected
+                            EntryAction::InsertInverse | EntryAction::Duplicate => Some(idx + stack::span(stack, idx)),
+                            _ => self.selected_entry.and_then(|i| map.get(i).copied().flatten()),
+                        };
+                        self.gizmo.release();
                     }
 
                     // final menu items
@@ -243,29 +568,461 @@ impl ApplicationState {
                         let rotation_clicked = ui.button("Rotation").clicked();
                         let scale_clicked = ui.button("Scale").clicked();
                         let translation_clicked = ui.button("Translation").clicked();
-                        let any_clicked = custom_clicked || rotation_clicked || scale_clicked || translation_clicked;
+                        let inverse_clicked = ui.button("Inverse of…").clicked();
+                        let exponential_clicked = ui.button("Exponential exp(tA)").clicked();
+                        let power_clicked = ui.button("Fractional power A^s").clicked();
+                        let homography_clicked = ui.button("Homography").clicked();
+                        let warp_clicked = ui.button("Warp f(x, y)").clicked();
+                        let mut complex_entry = None;
+                        if self.complex.enabled {
+                            ui.separator();
+                            let one = Complex::ONE;
+                            for interaction_type in [
+                                MatrixInteractionType::ComplexMultiply(Complex { re: 0.0, im: 1.0 }),
+                                MatrixInteractionType::ComplexAdd(Complex::ZERO),
+                                MatrixInteractionType::Reciprocal,
+                                MatrixInteractionType::Mobius { a: one, b: Complex::ZERO, c: one, d: one },
+                            ] {
+                                if ui.button(entry_label(&interaction_type)).clicked() {
+                                    complex_entry = Some(interaction_type);
+                                }
+                            }
+                        }
+                        let any_clicked = custom_clicked
+                            || rotation_clicked
+                            || scale_clicked
+                            || translation_clicked
+                            || inverse_clicked
+                            || exponential_clicked
+                            || power_clicked
+                            || homography_clicked
+                            || warp_clicked
+                            || complex_entry.is_some();
                         if custom_clicked {
-                            self.matrix_stack.push((Mat4::identity(), MatrixInteractionType::CustomMatrix, true));
+                            self.scene.stack_mut().push(StackEntry::new(MatrixInteractionType::CustomMatrix));
                         }
                         else if rotation_clicked {
-                            self.matrix_stack.push((Mat4::identity(), MatrixInteractionType::RotationMatrixZ(0.0), true));
+                            self.scene.stack_mut().push(StackEntry::new(MatrixInteractionType::RotationMatrixZ(0.0)));
                         }
                         else if scale_clicked {
-                            self.matrix_stack.push((Mat4::identity(), MatrixInteractionType::ScaleMatrix2D(Vec2::new(1.0,1.0)), true));
+                            self.scene.stack_mut().push(StackEntry::new(MatrixInteractionType::ScaleMatrix2D(Vec2::new(1.0,1.0))));
                         }
                         else if translation_clicked {
-                            self.matrix_stack.push((Mat4::identity(), MatrixInteractionType::TranslationMatrix2D(Vec2::new(0.0,0.0)), true));
+                            self.scene.stack_mut().push(StackEntry::new(MatrixInteractionType::TranslationMatrix2D(Vec2::new(0.0,0.0))));
+                        }
+                        else if inverse_clicked {
+                            let last = self.scene.stack().len().saturating_sub(1);
+                            self.scene.stack_mut().push(StackEntry::new(MatrixInteractionType::InverseOf { first: 0, last, condition: 1.0 }));
+                        }
+                        else if exponential_clicked {
+                            // start out with the generator of rotations, so exp(tA) is a rotation by t radians
+                            let mut generator = Mat4::zero();
+                            generator[(0, 1)] = -1.0;
+                            generator[(1, 0)] = 1.0;
+                            self.scene.stack_mut().push(StackEntry::new(MatrixInteractionType::Exponential { generator, t: 0.0, animate: None }));
+                        }
+                        else if power_clicked {
+                            self.scene.stack_mut().push(StackEntry::new(MatrixInteractionType::FractionalPower { base: Mat4::identity(), s: 1.0 }));
+                        }
+                        else if homography_clicked {
+                            // the corners of the default quad, with the top edge pulled in for a perspective look
+                            let source = [Vec2::new(-0.5, -0.5), Vec2::new(0.5, -0.5), Vec2::new(0.5, 0.5), Vec2::new(-0.5, 0.5)];
+                            let target = [Vec2::new(-0.5, -0.5), Vec2::new(0.5, -0.5), Vec2::new(0.3, 0.5), Vec2::new(-0.3, 0.5)];
+                            self.scene.stack_mut().push(StackEntry::new(MatrixInteractionType::Homography { source, target }));
+                        }
+                        else if warp_clicked {
+                            let (_, x, y) = expr::presets()[1];
+                            self.scene.stack_mut().push(StackEntry::new(MatrixInteractionType::Warp(WarpFunction::new(x, y))));
+                        }
+                        else if let Some(interaction_type) = complex_entry {
+                            self.scene.stack_mut().push(StackEntry::new(interaction_type));
                         }
                         if any_clicked {
+                            self.selected_entry = Some(self.scene.stack().len() - 1);
                             ui.close();
                         }
                     });
+                    // the world transform of the selected object, the shared stack and its parents included
+                    ui.menu_button("Copy transform as…", |ui| {
+                        export::copy_menu(ui, &self.scene.selected().model.transform, self.scene.convention, &mut self.export_precision);
+                    });
                     ui.checkbox(&mut self.show_full_matrix, "4x4 Matrices");
-                    ui.label("Matrices are applied from bottom to top.");
+                    ui.checkbox(&mut self.gizmo.visible, "Show gizmos");
+                    ui.checkbox(&mut self.show_jacobian, "Jacobian at cursor");
+                    let mut shape = None;
+                    // the drawn polygon and imported files are only offered once there are some
+                    let drawn = self.drawing.polygon().map(|points| Shape::Drawn { points: points.to_vec() });
+                    let model = &self.scene.selected().model;
+                    let current = model.shape.name();
+                    egui::ComboBox::from_label("Shape").selected_text(current).show_ui(ui, |ui| {
+                        for option in Shape::all().iter().chain(drawn.as_ref()).chain(self.import.shape.as_ref()) {
+                            if ui.selectable_label(option.name() == current, option.name()).clicked() && option.name() != current {
+                                shape = Some(option.clone());
+                            }
+                        }
+                    });
+                    if let Some(edited) = model.shape.gui(ui) {
+                        shape = Some(edited);
+                    }
+                    let mut subdivisions = model.subdivisions;
+                    ui.add(egui::Slider::new(&mut subdivisions, 1..=512).logarithmic(true).text("Subdivisions"));
+                    if shape.is_some() || subdivisions != model.subdivisions {
+                        changes.model = Some((shape.unwrap_or_else(|| model.shape.clone()), subdivisions));
+                    }
+                    if self.scene.is_warped(self.scene.selected) {
+                        self.warped_grid.gui(ui);
+                    }
+                    self.scene.convention.gui(ui);
                     ui.separator();
+                    self.split.gui(ui, &self.camera);
+                    ui.checkbox(&mut self.show_outliner, "Outliner");
+                    ui.checkbox(&mut self.basis.enabled, "Change of basis");
+                    ui.checkbox(&mut self.complex.enabled, "Complex plane");
+                    ui.checkbox(&mut self.dynamics.enabled, "Dynamical system");
+                    ui.checkbox(&mut self.ifs.enabled, "Iterated function system");
+                    ui.checkbox(&mut self.fit.enabled, "Fit transform to points");
+                    ui.checkbox(&mut self.probe.enabled, "Point probe");
+                    ui.checkbox(&mut self.drawing.enabled, "Draw polygon");
+                    ui.checkbox(&mut self.import.enabled, "Import model");
+                    ui.checkbox(&mut self.texture.show_window, "Texture");
+                    ui.checkbox(&mut self.points.enabled, "Point cloud");
                 });
+                if self.fit.enabled {
+                    self.fit_window(ctx, after_view);
+                }
+                // the overlays follow the selected object through its own and its parent stack
+                let selected = self.scene.selected;
+                if self.scene.is_warped(selected) {
+                    self.warped_grid.draw(&Overlay::new(ctx, &self.camera, after_view), |p| self.scene.map_point(selected, p));
+                }
+                // linearise the whole stack around the point under the cursor
+                let mouse = Vec2::new(self.input.current_mouse_pos.x, self.input.current_mouse_pos.y).as_();
+                if self.show_jacobian && after_view.contains(mouse) && !ctx.is_pointer_over_area() {
+                    let p = self.camera.screen_to_world(after_view.to_local(mouse), after_view.size);
+                    warp::draw_linearisation(&Overlay::new(ctx, &self.camera, after_view), |p| self.scene.map_point(selected, p), p);
+                }
+                if self.complex.enabled {
+                    self.complex_window(ctx, after_view);
+                }
+                if self.basis.enabled {
+                    self.basis_window(ctx, after_view);
+                }
+                if self.probe.enabled {
+                    self.probe_window(ctx, after_view);
+                }
+                if self.drawing.enabled {
+                    self.drawing_window(ctx, after_view, &mut changes);
+                }
+                if self.import.enabled {
+                    self.import_window(ctx, &mut changes);
+                }
+                if self.texture.show_window {
+                    self.texture_window(ctx, &mut changes);
+                }
+                if self.points.enabled {
+                    self.points_window(ctx, &mut changes);
+                }
+                if self.ifs.enabled {
+                    self.ifs_window(ctx);
+                }
+                if self.dynamics.enabled {
+                    self.dynamics_window(ctx, after_view);
+                }
+                if let Some(before_view) = before_view {
+                    let before = Overlay::new(ctx, self.split.before_camera(&self.camera), before_view);
+                    let after = Overlay::new(ctx, &self.camera, after_view);
+                    for (overlay, label) in [(before, "Before"), (after, "After")] {
+                        let corner = overlay.painter.clip_rect().left_top() + egui::vec2(8.0, 8.0);
+                        overlay.painter.text(corner, egui::Align2::LEFT_TOP, label, egui::FontId::proportional(16.0), egui::Color32::WHITE);
+                    }
+                }
+                if let Some(selected) = self.selected_entry {
+                    let above = self.scene.applied_after(selected);
+                    self.gizmo.draw(&Overlay::new(ctx, &self.camera, after_view), self.scene.stack(), selected, above);
+                }
             });
         self.renderer.gui_renderer.prepare(egui_output);
+        if let Some((shape, subdivisions)) = changes.model {
+            self.scene.set_model(&mut self.renderer, shape, subdivisions);
+        }
+        match changes.outliner {
+            Some(OutlinerAction::Add) => self.scene.add(&mut self.renderer, Shape::Square, 48),
+            Some(OutlinerAction::Duplicate) => self.scene.duplicate(&mut self.renderer, self.scene.selected),
+            Some(OutlinerAction::Remove) => self.scene.remove(&mut self.renderer, self.scene.selected),
+            Some(OutlinerAction::Select) | None => {}
+        }
+        if let Some(image) = changes.texture {
+            self.renderer.write_texture("model_texture", image.width, image.height, &image.rgba);
+        }
+        if changes.points {
+            add_point_cloud(&mut self.renderer, &self.points);
+        }
+    }
+
+    fn outliner_window(&mut self, ctx: &egui::Context, changes: &mut GuiChanges) {
+        let mut open = true;
+        egui::Window::new("Outliner")
+        .open(&mut open)
+        .default_width(128.0)
+        .show(ctx, |ui| {
+            // a different stack is being edited, its entries don't match the selection anymore
+            if let Some(action) = self.scene.gui(ui, self.show_full_matrix) {
+                self.selected_entry = None;
+                self.gizmo.release();
+                changes.outliner = Some(action);
+            }
+        });
+        self.show_outliner &= open;
+    }
+
+    fn fit_window(&mut self, ctx: &egui::Context, view: Viewport) {
+        let mut open = true;
+        egui::Window::new("Fit transform")
+        .open(&mut open)
+        .default_width(128.0)
+        .show(ctx, |ui| {
+            if let Some(matrix) = self.fit.gui(ui) {
+                self.scene.stack_mut().push(StackEntry::custom(matrix));
+                self.selected_entry = Some(self.scene.stack().len() - 1);
+            }
+        });
+        self.fit.enabled &= open;
+        self.fit.draw(&Overlay::new(ctx, &self.camera, view));
+    }
+
+    fn complex_window(&mut self, ctx: &egui::Context, view: Viewport) {
+        let mut open = true;
+        egui::Window::new("Complex plane")
+        .open(&mut open)
+        .default_width(128.0)
+        .show(ctx, |ui| {
+            self.complex.gui(ui);
+        });
+        self.complex.enabled &= open;
+        let selected = self.scene.selected;
+        self.complex.draw(&Overlay::new(ctx, &self.camera, view), |p| self.scene.map_point(selected, p));
+    }
+
+    fn basis_window(&mut self, ctx: &egui::Context, view: Viewport) {
+        let mut open = true;
+        egui::Window::new("Change of basis")
+        .open(&mut open)
+        .default_width(128.0)
+        .show(ctx, |ui| {
+            self.basis.gui(ui, &self.scene.selected().model.transform, self.show_full_matrix, self.scene.convention);
+        });
+        self.basis.enabled &= open;
+        self.basis.draw(&Overlay::new(ctx, &self.camera, view));
+    }
+
+    fn probe_window(&mut self, ctx: &egui::Context, view: Viewport) {
+        let mut open = true;
+        egui::Window::new("Point probe")
+        .open(&mut open)
+        .default_width(128.0)
+        .show(ctx, |ui| {
+            self.probe.gui(ui, self.scene.stack(), self.scene.convention);
+        });
+        self.probe.enabled &= open;
+        let overlay = Overlay::new(ctx, &self.camera, view);
+        self.probe.draw(&overlay, self.scene.stack(), self.scene.convention, self.scene.outer_transform());
+    }
+
+    fn drawing_window(&mut self, ctx: &egui::Context, view: Viewport, changes: &mut GuiChanges) {
+        let mut open = true;
+        egui::Window::new("Draw polygon")
+        .open(&mut open)
+        .default_width(128.0)
+        .show(ctx, |ui| {
+            let model = &self.scene.selected().model;
+            if self.drawing.gui(ui)
+                && matches!(model.shape, Shape::Drawn { .. })
+                && let Some(points) = self.drawing.polygon()
+            {
+                changes.model = Some((Shape::Drawn { points: points.to_vec() }, model.subdivisions));
+            }
+        });
+        self.drawing.enabled &= open;
+        self.drawing.draw(&Overlay::new(ctx, &self.camera, view));
+    }
+
+    fn import_window(&mut self, ctx: &egui::Context, changes: &mut GuiChanges) {
+        let mut open = true;
+        egui::Window::new("Import model")
+        .open(&mut open)
+        .default_width(128.0)
+        .show(ctx, |ui| {
+            if let Some(shape) = self.import.gui(ui) {
+                changes.model = Some((shape, self.scene.selected().model.subdivisions));
+            }
+            if let Some((name, image)) = self.import.take_texture() {
+                self.texture.use_model_texture(name);
+                changes.texture = Some(image);
+            }
+        });
+        self.import.enabled &= open;
+    }
+
+    fn texture_window(&mut self, ctx: &egui::Context, changes: &mut GuiChanges) {
+        let mut open = true;
+        egui::Window::new("Texture")
+        .open(&mut open)
+        .default_width(128.0)
+        .show(ctx, |ui| {
+            if let Some(image) = self.texture.gui(ui) {
+                changes.texture = Some(image);
+            }
+        });
+        self.texture.show_window &= open;
+    }
+
+    fn points_window(&mut self, ctx: &egui::Context, changes: &mut GuiChanges) {
+        let mut open = true;
+        let (scene, selected) = (&self.scene, self.scene.selected);
+        egui::Window::new("Point cloud")
+        .open(&mut open)
+        .default_width(128.0)
+        .show(ctx, |ui| {
+            changes.points = self.points.gui(ui, |p| scene.move_point(selected, p));
+        });
+        self.points.enabled &= open;
+    }
+
+    fn ifs_window(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        egui::Window::new("Iterated function system")
+        .open(&mut open)
+        .default_width(128.0)
+        .show(ctx, |ui| {
+            self.ifs.gui(ui);
+            ui.menu_button("Load preset", |ui| {
+                for preset in ifs::presets() {
+                    if ui.button(preset.name).clicked() {
+                        *self.scene.stack_mut() = preset
+                            .maps
+                            .into_iter()
+                            .map(|(map, ifs_weight)| StackEntry { ifs_weight, ..StackEntry::custom(map) })
+                            .collect();
+                        self.selected_entry = None;
+                        self.gizmo.release();
+                        ui.close();
+                    }
+                }
+            });
+            ui.separator();
+            ui.label("Probability weights:");
+            egui::Grid::new("ifs_weights").show(ui, |ui| {
+                let stack = self.scene.stack_mut();
+                let active = stack::active_entries(stack);
+                for ((idx, entry), active) in stack.iter_mut().enumerate().zip(active) {
+                    if !active {
+                        continue;
+                    }
+                    ui.label(format!("{idx}: {}", entry.label()));
+                    ui.add(egui::DragValue::new(&mut entry.ifs_weight).speed(0.01).range(0.0..=f32::INFINITY));
+                    ui.end_row();
+                }
+            });
+            // the chaos game fills each piece evenly when a map is picked in proportion to its area
+            if ui.button("Weights from |det|").clicked() {
+                for StackEntry { matrix: mat, ifs_weight, .. } in self.scene.stack_mut().iter_mut() {
+                    *ifs_weight = (mat[(0, 0)] * mat[(1, 1)] - mat[(0, 1)] * mat[(1, 0)]).abs().max(0.01);
+                }
+            }
+            let active_count = stack::active_entries(self.scene.stack()).into_iter().filter(|active| *active).count();
+            if active_count > ifs::MAX_MAPS {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("Only the first {} active entries are used", ifs::MAX_MAPS));
+            }
+        });
+        self.ifs.enabled &= open;
+    }
+
+    fn dynamics_window(&mut self, ctx: &egui::Context, view: Viewport) {
+        let mut open = true;
+        egui::Window::new("Dynamical system")
+        .open(&mut open)
+        .default_width(128.0)
+        .show(ctx, |ui| {
+            self.dynamics.gui(ui, &self.scene.selected().model.transform);
+        });
+        self.dynamics.enabled &= open;
+        let overlay = Overlay::new(ctx, &self.camera, view);
+        self.dynamics.draw(&overlay, &self.scene.selected().model.transform);
+    }
+
+    // pick up gizmo handles of the selected entry or basis vector tips, and move them with the cursor
+    fn drag_handles(&mut self) {
+        let ctx = self.renderer.gui_renderer.input_state.egui_ctx();
+        let window_size = Vec2::new(self.renderer.size.width, self.renderer.size.height).as_();
+        let mouse = Vec2::new(self.input.current_mouse_pos.x, self.input.current_mouse_pos.y).as_();
+        // gizmos live in the view showing the transformed model
+        let (_, view) = self.split.layout(window_size);
+        if !self.input.clicking[0] {
+            self.gizmo.release();
+            self.basis.release();
+            self.drawing.release();
+        }
+        if self.input.just_pressed[0] && !ctx.is_pointer_over_area() && view.contains(mouse) {
+            if let Some(selected) = self.selected_entry {
+                let above = self.scene.applied_after(selected);
+                let (local, ppp) = (view.to_local(mouse), ctx.pixels_per_point());
+                self.gizmo.grab(self.scene.stack(), selected, above, local, &self.camera, view.size, ppp);
+            }
+            if !self.gizmo.is_dragging() {
+                self.basis.grab(view.to_local(mouse), &self.camera, view.size, ctx.pixels_per_point());
+            }
+            if !self.gizmo.is_dragging() && !self.basis.is_dragging() {
+                self.drawing.grab(view.to_local(mouse), &self.camera, view.size, ctx.pixels_per_point());
+            }
+        }
+        if let Some(entry) = self.gizmo.dragged_entry() {
+            let above = self.scene.applied_after(entry);
+            self.gizmo.drag(self.scene.stack_mut(), above, view.to_local(mouse), &self.camera, view.size);
+        }
+        self.basis.drag(view.to_local(mouse), &self.camera, view.size);
+        // the model follows the edited polygon while it is showing it
+        if self.drawing.drag(view.to_local(mouse), &self.camera, view.size) && matches!(self.scene.selected().model.shape, Shape::Drawn { .. }) {
+            self.use_drawn_polygon();
+        }
+    }
+
+    // a handle on the canvas follows the cursor, so it shouldn't pan or place points
+    fn handle_grabbed(&self) -> bool {
+        self.gizmo.is_dragging() || self.basis.is_dragging() || self.drawing.is_dragging()
+    }
+
+    // rebuild the model from the outline of the drawing tool, if it can be filled
+    fn use_drawn_polygon(&mut self) {
+        if let Some(points) = self.drawing.polygon() {
+            let shape = Shape::Drawn { points: points.to_vec() };
+            let subdivisions = self.scene.selected().model.subdivisions;
+            self.scene.set_model(&mut self.renderer, shape, subdivisions);
+        }
+    }
+
+    // sweep the parameter of animated entries back and forth over [0, 1]
+    fn animate_entries(&mut self, dt: f32) {
+        const ANIMATION_SPEED: f32 = 0.25; // parameter units per second
+        for entry in self.scene.stacks_mut().flatten() {
+            if let MatrixInteractionType::Exponential { t, animate: Some(direction), .. } = &mut entry.interaction_type {
+                *t += *direction * ANIMATION_SPEED * dt;
+                if *t >= 1.0 {
+                    *t = 1.0;
+                    *direction = -1.0;
+                } else if *t <= 0.0 {
+                    *t = 0.0;
+                    *direction = 1.0;
+                }
+            }
+        }
+    }
+
+    // compute the matrices of entries that depend on other entries in their stack
+    fn resolve_references(&mut self) {
+        let convention = self.scene.convention;
+        for stack in self.scene.stacks_mut() {
+            resolve_references(stack, convention);
+        }
     }
 
 
@@ -276,10 +1033,40 @@ impl ApplicationState {
         let dt_seconds = delta.as_secs_f64();
         trace!("Update called with dt={}", dt_seconds);
 
-        if !self.renderer.gui_renderer.input_state.egui_ctx().is_using_pointer() {
-            self.camera.pan(&self.input, Vec2::new(self.renderer.size.width, self.renderer.size.height).as_());
+        let window_size = Vec2::new(self.renderer.size.width, self.renderer.size.height).as_();
+        let mouse = Vec2::new(self.input.current_mouse_pos.x, self.input.current_mouse_pos.y).as_();
+        let (before_view, after_view) = self.split.layout(window_size);
+        let in_before = before_view.is_some_and(|view| view.contains(mouse));
+
+        self.drag_handles();
+        if !self.renderer.gui_renderer.input_state.egui_ctx().is_using_pointer() && !self.handle_grabbed() {
+            // pan and zoom whichever view is under the cursor
+            let camera = self.split.camera_under_cursor(&mut self.camera, in_before, self.input.clicking[0]);
+            camera.pan(&self.input, after_view.size);
             if !self.renderer.gui_renderer.input_state.egui_ctx().is_pointer_over_area() {
-                self.camera.zoom(&self.input);
+                camera.zoom(&self.input);
+            }
+        }
+
+        // clicks on the canvas only act in the view showing the transformed model
+        let over_gui = self.renderer.gui_renderer.input_state.egui_ctx().is_pointer_over_area() || !after_view.contains(mouse);
+        let local_mouse = after_view.to_local(mouse);
+        // right clicking the canvas places seed points for the dynamical system
+        if self.dynamics.enabled && self.input.just_pressed[1] && !over_gui {
+            self.dynamics.seeds.push(self.camera.screen_to_world(local_mouse, after_view.size));
+        }
+        // left clicking places probes, point pairs to fit a transform to or polygon vertices, unless a gizmo handle was grabbed
+        if self.input.just_pressed[0] && !over_gui && !self.handle_grabbed() {
+            let world = self.camera.screen_to_world(local_mouse, after_view.size);
+            if self.probe.enabled && self.probe.placing {
+                self.probe.place(world);
+            } else if self.fit.enabled && self.fit.placing {
+                self.fit.place(world);
+            } else if self.drawing.enabled
+                && self.drawing.placing
+                && self.drawing.click(world, &self.camera, after_view.size, self.renderer.gui_renderer.input_state.egui_ctx().pixels_per_point())
+            {
+                self.use_drawn_polygon();
             }
         }
 
@@ -288,13 +1075,143 @@ impl ApplicationState {
         let view_proj = self.camera.get_matrix(self.renderer.aspect());
         let mat = view_proj;
 
-        self.model.transform = self.matrix_stack.iter().fold(Mat4::identity(), |acc, m| {acc * if m.2 {m.0} else {Mat4::identity()}}); 
+        self.animate_entries(dt_seconds as f32);
+        self.resolve_references();
+        self.scene.update_transforms();
 
         self.renderer.write_buffer("camera", bytemuck::cast_slice(mat.as_col_slice()));
         
-        self.renderer.write_buffer("transform", bytemuck::cast_slice(self.model.transform.as_col_slice()));
+        // a non-linear stack moves the vertices of the warped copy of a model instead of using its transform
+        for (i, object) in self.scene.objects.iter().enumerate() {
+            let (model, slot) = (&object.model, self.scene.slot(i));
+            if self.scene.is_warped(i) {
+                let (vertices, indices) = warp::warp_mesh(&model.vertices, &model.indices, |p| self.scene.map_point(i, p));
+                self.renderer.update_renderable(&Model::warped_name(slot), &vertices, &indices);
+            } else if model.shape.is_3d() {
+                let indices = shapes::sort_back_to_front(&model.vertices, &model.indices, model.transform);
+                self.renderer.update_renderable(&Model::name(slot), &model.vertices, &indices);
+            }
+        }
+        // the point cloud moves with the selected object
+        let selected = self.scene.selected;
+        let warped = self.scene.is_warped(selected);
+        let transforms = self.scene.gpu_transforms();
+        let show_points = self.points.enabled && !self.points.is_empty();
+        if show_points && warped {
+            let instances = self.points.instances(|p| self.scene.move_point(selected, p));
+            self.renderer.update_instances("warped_point_cloud", &instances);
+        } else if show_points && self.points.is_3d() {
+            let mut instances = self.points.instances(|p| p);
+            points::sort_back_to_front(&mut instances, transforms[POINT_CLOUD_SLOT as usize]);
+            self.renderer.update_instances("point_cloud", &instances);
+        }
+        self.renderer.write_buffer("transform", &transform_slots(&transforms));
 
+        // the ifs replaces the models while it is shown. the "before" view of a split always uses the plain models
+        // a point cloud takes the place of the models, unless both are asked for
+        let show_model = !self.ifs.enabled && (!show_points || self.points.show_model);
+        let show_points = !self.ifs.enabled && show_points;
+        self.renderer.set_pass_enabled("Default", show_model);
+        self.renderer.set_pass_enabled("Warped", show_model);
+        self.renderer.set_pass_enabled("Points", show_points && (!warped || before_view.is_some()));
+        self.renderer.set_pass_enabled("Warped points", show_points && warped);
+        self.renderer.set_ifs_point_count(if self.ifs.enabled { self.ifs.point_count } else { 0 });
+        if self.ifs.enabled {
+            let stack = self.scene.stack();
+            let maps = stack.iter().zip(stack::active_entries(stack)).filter(|(_, active)| *active).map(|(e, _)| (e.matrix, e.ifs_weight));
+            self.renderer.write_buffer("ifs", bytemuck::cast_slice(&[self.ifs.data(maps)]));
+        }
+
+        self.renderer.write_buffer("basis", bytemuck::cast_slice(&[self.basis.data()]));
+        self.renderer.write_buffer("texturing", bytemuck::cast_slice(&self.texture.data()));
         self.renderer.write_buffer("camera_pan_zoom", bytemuck::cast_slice(&[self.camera.pan_and_zoom_data(self.renderer.aspect())]));
+
+        // each view gets its own camera, the "before" view shows the models without their stacks applied.
+        // each model is drawn either as it is or as its warped copy, and hidden objects not at all
+        let mut after_hidden = Vec::new();
+        let mut before_hidden = Vec::new();
+        for (i, object) in self.scene.objects.iter().enumerate() {
+            let (slot, warped) = (self.scene.slot(i), self.scene.is_warped(i));
+            if !object.visible || warped {
+                after_hidden.push(Model::name(slot));
+            }
+            if !object.visible || !warped {
+                after_hidden.push(Model::warped_name(slot));
+            }
+            if !object.visible {
+                before_hidden.push(Model::name(slot));
+            }
+        }
+        let after_passes = if warped { vec!["Points".to_string()] } else { Vec::new() };
+        let after = (after_view, &self.camera, transform_slots(&transforms), after_passes, after_hidden);
+        let before = before_view.map(|before_view| {
+            let identities = vec![Mat4::identity(); transforms.len()];
+            let before_passes = vec!["Warped".to_string(), "Warped points".to_string()];
+            (before_view, self.split.before_camera(&self.camera), transform_slots(&identities), before_passes, before_hidden)
+        });
+        let views = before
+            .into_iter()
+            .chain([after])
+            .map(|(view, camera, transforms, hidden_passes, hidden_renderables)| View {
+                origin: view.origin,
+                size: view.size,
+                buffers: vec![
+                    ("camera".into(), bytemuck::cast_slice(camera.get_matrix(view.aspect()).as_col_slice()).to_vec()),
+                    ("transform".into(), transforms),
+                    ("camera_pan_zoom".into(), bytemuck::cast_slice(&[camera.pan_and_zoom_data(view.aspect())]).to_vec()),
+                ],
+                hidden_passes,
+                hidden_renderables,
+            })
+            .collect();
+        self.renderer.set_views(views);
+    }
+
+}
+
+// edits of the stack asked for by an entry, done after all entries are shown
+#[derive(Clone, Copy)]
+enum EntryAction {
+    Remove,
+    InsertInverse,
+    Duplicate,
+    Group,
+    ExtendGroup,
+    Ungroup,
+}
+
+// compute the matrices of entries that depend on other entries in the stack
+fn resolve_references(stack: &mut [StackEntry], convention: Convention) {
+    let Some(max_index) = stack.len().checked_sub(1) else {
+        return;
+    };
+    for idx in 0..stack.len() {
+        let MatrixInteractionType::InverseOf { first, last, .. } = stack[idx].interaction_type else {
+            continue;
+        };
+        let (first, last) = (first.min(last).min(max_index), first.max(last).min(max_index));
+        let inversion = if (first..=last).contains(&idx) {
+            linalg::Inversion { inverse: None, condition: f64::INFINITY }
+        } else {
+            linalg::invert(&stack::range_product(stack, first..last + 1, convention))
+        };
+        let StackEntry { matrix: mat, interaction_type, .. } = &mut stack[idx];
+        *mat = inversion.inverse.unwrap_or(Mat4::identity());
+        if let MatrixInteractionType::InverseOf { condition, .. } = interaction_type {
+            *condition = inversion.condition;
+        }
     }
+}
+
+// the transforms of the object buffer, each in its own slot
+fn transform_slots(transforms: &[Mat4<f32>]) -> Vec<u8> {
+    object_slots(transforms.iter().map(|transform| bytemuck::cast_slice(transform.as_col_slice())))
+}
 
+// (re)build the sprites of the point cloud, once as they are and once to be moved by a warped stack
+fn add_point_cloud(renderer: &mut Renderer, points: &PointCloud) {
+    let (vertices, indices) = points::sprite();
+    let instances = points.instances(|p| p);
+    renderer.add_instanced_renderable("point_cloud".into(), "Points".into(), &indices, &vertices, &instances, POINT_CLOUD_SLOT);
+    renderer.add_instanced_renderable("warped_point_cloud".into(), "Warped points".into(), &indices, &vertices, &instances, POINT_CLOUD_SLOT);
 }
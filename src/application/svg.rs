@@ -0,0 +1,631 @@
+use std::f32::consts::TAU;
+
+use vek::{Vec2, Vec4};
+
+use super::mesh;
+
+// a filled or stroked part of a drawing, as triangles in the order they are painted
+#[derive(Clone, PartialEq)]
+pub struct Piece {
+    pub color: Vec4<f32>,
+    pub points: Vec<Vec2<f32>>,
+    pub indices: Vec<u32>,
+}
+
+// the affine map [a c e; b d f] in svg's order
+type Transform = [f32; 6];
+
+const IDENTITY: Transform = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+fn compose(m: &Transform, n: &Transform) -> Transform {
+    [
+        m[0] * n[0] + m[2] * n[1],
+        m[1] * n[0] + m[3] * n[1],
+        m[0] * n[2] + m[2] * n[3],
+        m[1] * n[2] + m[3] * n[3],
+        m[0] * n[4] + m[2] * n[5] + m[4],
+        m[1] * n[4] + m[3] * n[5] + m[5],
+    ]
+}
+
+fn apply(m: &Transform, p: Vec2<f32>) -> Vec2<f32> {
+    Vec2::new(m[0] * p.x + m[2] * p.y + m[4], m[1] * p.x + m[3] * p.y + m[5])
+}
+
+// how much the map stretches lengths on average
+fn scale(m: &Transform) -> f32 {
+    (m[0] * m[3] - m[1] * m[2]).abs().sqrt().max(f32::EPSILON)
+}
+
+// the painting attributes that groups pass on to their children
+#[derive(Clone)]
+struct Style {
+    fill: Option<Vec4<f32>>,
+    stroke: Option<Vec4<f32>>,
+    stroke_width: f32,
+    transform: Transform,
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+// #rgb, #rrggbb, rgb(r, g, b) and a few names. None for "none"
+pub fn parse_color(value: &str) -> Result<Option<Vec4<f32>>, String> {
+    let value = value.trim().to_lowercase();
+    let rgb: [f32; 3] = if let Some(hex) = value.strip_prefix('#') {
+        let digit = |i: usize, len: usize| u8::from_str_radix(&hex[i * len..(i + 1) * len], 16).map(|v| if len == 1 { v * 17 } else { v } as f32 / 255.0);
+        // checked before slicing, so the slices can't cut through a character
+        let len = match hex.len() {
+            3 => 1,
+            6 => 2,
+            _ => return Err(format!("Invalid colour \"{value}\"")),
+        };
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(format!("Invalid colour \"{value}\""));
+        }
+        [0, 1, 2].map(|i| digit(i, len).unwrap_or(0.0))
+    } else if let Some(args) = value.strip_prefix("rgb(").and_then(|v| v.strip_suffix(')')) {
+        let channels = args
+            .split(',')
+            .map(|c| {
+                let c = c.trim();
+                match c.strip_suffix('%') {
+                    Some(percent) => percent.parse::<f32>().map(|p| p / 100.0),
+                    None => c.parse::<f32>().map(|v| v / 255.0),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("Invalid colour \"{value}\""))?;
+        match channels[..] {
+            [r, g, b] => [r, g, b],
+            _ => return Err(format!("Invalid colour \"{value}\"")),
+        }
+    } else {
+        match value.as_str() {
+            "none" | "transparent" => return Ok(None),
+            "black" | "currentcolor" => [0.0, 0.0, 0.0],
+            "white" => [1.0, 1.0, 1.0],
+            "red" => [1.0, 0.0, 0.0],
+            "green" => [0.0, 0.5, 0.0],
+            "lime" => [0.0, 1.0, 0.0],
+            "blue" => [0.0, 0.0, 1.0],
+            "yellow" => [1.0, 1.0, 0.0],
+            "cyan" | "aqua" => [0.0, 1.0, 1.0],
+            "magenta" | "fuchsia" => [1.0, 0.0, 1.0],
+            "orange" => [1.0, 0.65, 0.0],
+            "purple" => [0.5, 0.0, 0.5],
+            "gray" | "grey" => [0.5, 0.5, 0.5],
+            // gradients and patterns aren't supported, paint them grey so the shape still shows
+            _ if value.starts_with("url(") => [0.5, 0.5, 0.5],
+            _ => return Err(format!("Unknown colour \"{value}\"")),
+        }
+    };
+    Ok(Some(Vec4::new(srgb_to_linear(rgb[0]), srgb_to_linear(rgb[1]), srgb_to_linear(rgb[2]), 1.0)))
+}
+
+// numbers in attribute values and path data, which may be run together like "1.5.5-2"
+fn numbers(value: &str) -> Result<Vec<f32>, String> {
+    let mut numbers = Vec::new();
+    let mut number = String::new();
+    let flush = |number: &mut String, numbers: &mut Vec<f32>| -> Result<(), String> {
+        if !number.is_empty() {
+            numbers.push(number.parse().map_err(|_| format!("Invalid number \"{number}\""))?);
+            number.clear();
+        }
+        Ok(())
+    };
+    for c in value.chars() {
+        let starts_new = match c {
+            '-' | '+' => !number.ends_with(['e', 'E']),
+            '.' => number.contains('.') && !number.contains(['e', 'E']),
+            _ => false,
+        };
+        if starts_new || c.is_whitespace() || c == ',' {
+            flush(&mut number, &mut numbers)?;
+        }
+        if !c.is_whitespace() && c != ',' {
+            number.push(c);
+        }
+    }
+    flush(&mut number, &mut numbers)?;
+    Ok(numbers)
+}
+
+// a list like "translate(10 20) rotate(45)"
+fn parse_transform(value: &str) -> Result<Transform, String> {
+    let mut transform = IDENTITY;
+    for part in value.split(')').map(str::trim).filter(|part| !part.is_empty()) {
+        let (name, args) = part.split_once('(').ok_or(format!("Invalid transform \"{value}\""))?;
+        let args = numbers(args)?;
+        let arg = |i: usize| args.get(i).copied();
+        let invalid = || format!("Invalid transform \"{part})\"");
+        let next = match (name.trim().trim_start_matches(',').trim(), args.len()) {
+            ("matrix", 6) => [args[0], args[1], args[2], args[3], args[4], args[5]],
+            ("translate", 1 | 2) => [1.0, 0.0, 0.0, 1.0, args[0], arg(1).unwrap_or(0.0)],
+            ("scale", 1 | 2) => [args[0], 0.0, 0.0, arg(1).unwrap_or(args[0]), 0.0, 0.0],
+            ("rotate", 1 | 3) => {
+                let (sin, cos) = args[0].to_radians().sin_cos();
+                let (cx, cy) = (arg(1).unwrap_or(0.0), arg(2).unwrap_or(0.0));
+                // rotation about (cx, cy)
+                [cos, sin, -sin, cos, cx - cos * cx + sin * cy, cy - sin * cx - cos * cy]
+            }
+            ("skewX", 1) => [1.0, 0.0, args[0].to_radians().tan(), 1.0, 0.0, 0.0],
+            ("skewY", 1) => [1.0, args[0].to_radians().tan(), 0.0, 1.0, 0.0, 0.0],
+            _ => return Err(invalid()),
+        };
+        transform = compose(&transform, &next);
+    }
+    Ok(transform)
+}
+
+// a flattened subpath, and whether it was closed
+type Polyline = (Vec<Vec2<f32>>, bool);
+
+// flattens path data into polylines
+struct PathBuilder {
+    subpaths: Vec<Polyline>,
+    current: Vec2<f32>,
+    start: Vec2<f32>,
+    tolerance: f32,
+}
+
+impl PathBuilder {
+    fn new(tolerance: f32) -> Self {
+        Self {
+            subpaths: Vec::new(),
+            current: Vec2::zero(),
+            start: Vec2::zero(),
+            tolerance,
+        }
+    }
+
+    fn move_to(&mut self, p: Vec2<f32>) {
+        self.subpaths.push((vec![p], false));
+        self.current = p;
+        self.start = p;
+    }
+
+    fn line_to(&mut self, p: Vec2<f32>) {
+        if self.subpaths.last().is_none_or(|(_, closed)| *closed) {
+            let current = self.current;
+            self.move_to(current);
+        }
+        if let Some((points, _)) = self.subpaths.last_mut() {
+            points.push(p);
+        }
+        self.current = p;
+    }
+
+    fn close(&mut self) {
+        if let Some((_, closed)) = self.subpaths.last_mut() {
+            *closed = true;
+        }
+        self.current = self.start;
+    }
+
+    // enough segments to stay within the tolerance, from the second differences of the control points
+    fn segments(&self, bend: f32, factor: f32) -> usize {
+        ((factor * bend / self.tolerance).sqrt().ceil() as usize).clamp(1, 1000)
+    }
+
+    fn quadratic_to(&mut self, c: Vec2<f32>, p: Vec2<f32>) {
+        let a = self.current;
+        let n = self.segments((a - c * 2.0 + p).magnitude(), 0.25);
+        for i in 1..=n {
+            let t = i as f32 / n as f32;
+            self.line_to(a * (1.0 - t) * (1.0 - t) + c * 2.0 * t * (1.0 - t) + p * t * t);
+        }
+    }
+
+    fn cubic_to(&mut self, c1: Vec2<f32>, c2: Vec2<f32>, p: Vec2<f32>) {
+        let a = self.current;
+        let bend = (a - c1 * 2.0 + c2).magnitude().max((c1 - c2 * 2.0 + p).magnitude());
+        let n = self.segments(bend, 0.75);
+        for i in 1..=n {
+            let t = i as f32 / n as f32;
+            let s = 1.0 - t;
+            self.line_to(a * s * s * s + c1 * 3.0 * s * s * t + c2 * 3.0 * s * t * t + p * t * t * t);
+        }
+    }
+
+    // elliptical arc in endpoint form, converted to its center as in the svg implementation notes
+    fn arc_to(&mut self, radii: Vec2<f32>, rotation: f32, large_arc: bool, sweep: bool, p: Vec2<f32>) {
+        let a = self.current;
+        let (mut rx, mut ry) = (radii.x.abs(), radii.y.abs());
+        if rx <= f32::EPSILON || ry <= f32::EPSILON || a == p {
+            self.line_to(p);
+            return;
+        }
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        let half = (a - p) / 2.0;
+        let a1 = Vec2::new(cos * half.x + sin * half.y, -sin * half.x + cos * half.y);
+        // radii that are too small are scaled up until the arc fits
+        let lambda = (a1.x / rx).powi(2) + (a1.y / ry).powi(2);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+        let numerator = (rx * rx * ry * ry - rx * rx * a1.y * a1.y - ry * ry * a1.x * a1.x).max(0.0);
+        let mut root = (numerator / (rx * rx * a1.y * a1.y + ry * ry * a1.x * a1.x)).sqrt();
+        if large_arc == sweep {
+            root = -root;
+        }
+        let c1 = Vec2::new(root * rx * a1.y / ry, -root * ry * a1.x / rx);
+        let mid = (a + p) / 2.0;
+        let center = Vec2::new(cos * c1.x - sin * c1.y + mid.x, sin * c1.x + cos * c1.y + mid.y);
+        let angle = |v: Vec2<f32>| v.y.atan2(v.x);
+        let theta = angle(Vec2::new((a1.x - c1.x) / rx, (a1.y - c1.y) / ry));
+        let mut delta = angle(Vec2::new((-a1.x - c1.x) / rx, (-a1.y - c1.y) / ry)) - theta;
+        if sweep && delta < 0.0 {
+            delta += TAU;
+        } else if !sweep && delta > 0.0 {
+            delta -= TAU;
+        }
+        // the sagitta of each step stays below the tolerance
+        let step = 2.0 * (1.0 - (self.tolerance / rx.max(ry)).min(1.0)).acos();
+        let n = ((delta.abs() / step.max(1e-3)).ceil() as usize).clamp(1, 1000);
+        for i in 1..n {
+            let t = theta + delta * i as f32 / n as f32;
+            let e = Vec2::new(rx * t.cos(), ry * t.sin());
+            self.line_to(Vec2::new(cos * e.x - sin * e.y, sin * e.x + cos * e.y) + center);
+        }
+        self.line_to(p);
+    }
+}
+
+fn parse_path(data: &str, tolerance: f32) -> Result<Vec<Polyline>, String> {
+    let mut path = PathBuilder::new(tolerance);
+    // commands with the numbers that follow them
+    let mut commands = Vec::new();
+    let mut rest = data.trim();
+    while let Some(c) = rest.chars().next() {
+        if !c.is_ascii_alphabetic() || c == 'e' || c == 'E' {
+            return Err(format!("Path data should start with a command, got '{c}'"));
+        }
+        let end = rest[1..].find(|c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E').map_or(rest.len(), |i| i + 1);
+        commands.push((c, numbers(&rest[1..end])?));
+        rest = rest[end..].trim_start();
+    }
+    // control point of the previous curve, for the smooth variants
+    let mut last_control: Option<(char, Vec2<f32>)> = None;
+    for (command, args) in commands {
+        let relative = command.is_ascii_lowercase();
+        let arity = match command.to_ascii_uppercase() {
+            'Z' => 0,
+            'H' | 'V' => 1,
+            'M' | 'L' | 'T' => 2,
+            'S' | 'Q' => 4,
+            'C' => 6,
+            'A' => 7,
+            _ => return Err(format!("Unknown path command '{command}'")),
+        };
+        if arity == 0 {
+            path.close();
+            last_control = None;
+            continue;
+        }
+        if args.is_empty() || args.len() % arity != 0 {
+            return Err(format!("Path command '{command}' takes {arity} numbers at a time, got {}", args.len()));
+        }
+        for (n, args) in args.chunks(arity).enumerate() {
+            let origin = if relative { path.current } else { Vec2::zero() };
+            let point = |i: usize| origin + Vec2::new(args[i], args[i + 1]);
+            let reflected = |kinds: &str| match last_control {
+                Some((kind, control)) if kinds.contains(kind) => path.current * 2.0 - control,
+                _ => path.current,
+            };
+            let mut control = None;
+            match command.to_ascii_uppercase() {
+                // coordinate pairs after the first one of a move are lines
+                'M' if n == 0 => path.move_to(point(0)),
+                'M' | 'L' => path.line_to(point(0)),
+                'H' => path.line_to(Vec2::new(args[0] + if relative { path.current.x } else { 0.0 }, path.current.y)),
+                'V' => path.line_to(Vec2::new(path.current.x, args[0] + if relative { path.current.y } else { 0.0 })),
+                'Q' => {
+                    control = Some(('Q', point(0)));
+                    path.quadratic_to(point(0), point(2));
+                }
+                'T' => {
+                    let c = reflected("QT");
+                    control = Some(('T', c));
+                    path.quadratic_to(c, point(0));
+                }
+                'C' => {
+                    control = Some(('C', point(2)));
+                    path.cubic_to(point(0), point(2), point(4));
+                }
+                'S' => {
+                    let c1 = reflected("CS");
+                    control = Some(('S', point(0)));
+                    path.cubic_to(c1, point(0), point(2));
+                }
+                _ => path.arc_to(Vec2::new(args[0], args[1]), args[2], args[3] != 0.0, args[4] != 0.0, point(5)),
+            }
+            last_control = control;
+        }
+    }
+    Ok(path.subpaths)
+}
+
+// the outlines of a basic shape element
+fn element_outlines(tag: &str, attribute: &dyn Fn(&str) -> Option<String>, tolerance: f32) -> Result<Vec<Polyline>, String> {
+    let number = |name: &str| -> Result<f32, String> {
+        match attribute(name) {
+            Some(value) => value.trim().trim_end_matches("px").parse().map_err(|_| format!("Invalid {name} \"{value}\" on <{tag}>")),
+            None => Ok(0.0),
+        }
+    };
+    let ellipse = |center: Vec2<f32>, rx: f32, ry: f32| {
+        let mut path = PathBuilder::new(tolerance);
+        path.move_to(center + Vec2::new(rx, 0.0));
+        path.arc_to(Vec2::new(rx, ry), 0.0, false, true, center - Vec2::new(rx, 0.0));
+        path.arc_to(Vec2::new(rx, ry), 0.0, false, true, center + Vec2::new(rx, 0.0));
+        path.close();
+        path.subpaths
+    };
+    let points = || -> Result<Vec<Vec2<f32>>, String> {
+        Ok(numbers(&attribute("points").unwrap_or_default())?.chunks_exact(2).map(|p| Vec2::new(p[0], p[1])).collect())
+    };
+    Ok(match tag {
+        "path" => parse_path(&attribute("d").unwrap_or_default(), tolerance)?,
+        "polygon" => vec![(points()?, true)],
+        "polyline" => vec![(points()?, false)],
+        "line" => vec![(vec![Vec2::new(number("x1")?, number("y1")?), Vec2::new(number("x2")?, number("y2")?)], false)],
+        "rect" => {
+            let (x, y, w, h) = (number("x")?, number("y")?, number("width")?, number("height")?);
+            vec![(vec![Vec2::new(x, y), Vec2::new(x + w, y), Vec2::new(x + w, y + h), Vec2::new(x, y + h)], true)]
+        }
+        "circle" => ellipse(Vec2::new(number("cx")?, number("cy")?), number("r")?, number("r")?),
+        "ellipse" => ellipse(Vec2::new(number("cx")?, number("cy")?), number("rx")?, number("ry")?),
+        _ => Vec::new(),
+    })
+}
+
+// outlines inside an odd number of others are holes in the one directly around them
+fn fill(outlines: &[Vec<Vec2<f32>>], color: Vec4<f32>) -> Option<Piece> {
+    let depth = |i: usize| (0..outlines.len()).filter(|j| *j != i && mesh::contains(&outlines[*j], outlines[i][0])).count();
+    let mut piece = Piece { color, points: Vec::new(), indices: Vec::new() };
+    for (i, outline) in outlines.iter().enumerate().filter(|(i, _)| depth(*i) % 2 == 0) {
+        let holes = outlines
+            .iter()
+            .enumerate()
+            .filter(|(j, hole)| depth(*j) == depth(i) + 1 && mesh::contains(outline, hole[0]))
+            .map(|(_, hole)| hole.clone())
+            .collect::<Vec<_>>();
+        let merged = mesh::bridge_holes(outline, &holes);
+        let indices = mesh::triangulate(&merged)?;
+        let offset = piece.points.len() as u32;
+        piece.indices.extend(indices.into_iter().map(|i| i + offset));
+        piece.points.extend(merged);
+    }
+    Some(piece)
+}
+
+// a quad along each segment and a disc on each joint
+fn stroke(polyline: &[Vec2<f32>], closed: bool, width: f32, color: Vec4<f32>) -> Piece {
+    const JOINT_SEGMENTS: usize = 12;
+    let mut piece = Piece { color, points: Vec::new(), indices: Vec::new() };
+    let half = width / 2.0;
+    let segment_count = if closed { polyline.len() } else { polyline.len() - 1 };
+    for i in 0..segment_count {
+        let (a, b) = (polyline[i], polyline[(i + 1) % polyline.len()]);
+        let Some(direction) = (b - a).try_normalized() else {
+            continue;
+        };
+        let normal = Vec2::new(-direction.y, direction.x) * half;
+        let first = piece.points.len() as u32;
+        piece.points.extend([a + normal, b + normal, b - normal, a - normal]);
+        piece.indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+    for (i, joint) in polyline.iter().enumerate() {
+        if !closed && (i == 0 || i == polyline.len() - 1) {
+            continue;
+        }
+        let center = piece.points.len() as u32;
+        piece.points.push(*joint);
+        for k in 0..JOINT_SEGMENTS {
+            let t = k as f32 / JOINT_SEGMENTS as f32 * TAU;
+            piece.points.push(*joint + Vec2::new(t.cos(), t.sin()) * half);
+            piece.indices.extend([center, center + 1 + k as u32, center + 1 + ((k + 1) % JOINT_SEGMENTS) as u32]);
+        }
+    }
+    piece
+}
+
+// the attributes of a tag like <rect x="1" style="fill: red"/>, with style properties overriding
+fn attributes(tag: &str) -> Result<Vec<(String, String)>, String> {
+    let mut attributes = Vec::new();
+    let mut rest = tag;
+    while let Some(eq) = rest.find('=') {
+        let name = rest[..eq].trim().to_string();
+        let value = rest[eq + 1..].trim_start();
+        let quote = value.chars().next().filter(|q| *q == '"' || *q == '\'').ok_or(format!("Unquoted value of \"{name}\""))?;
+        let end = value[1..].find(quote).ok_or(format!("Unterminated value of \"{name}\""))? + 1;
+        attributes.push((name, value[1..end].to_string()));
+        rest = &value[end + 1..];
+    }
+    let style = attributes.iter().find(|(name, _)| name == "style").map(|(_, style)| style.clone());
+    for property in style.iter().flat_map(|style| style.split(';')) {
+        if let Some((name, value)) = property.split_once(':') {
+            attributes.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    Ok(attributes)
+}
+
+// parses the supported subset of svg: paths, polygons, polylines, lines, rects, circles and ellipses,
+// in nested groups with fill, stroke, stroke-width and transform. the tolerance is relative to the
+// size of the drawing, and the result is centered and scaled to fit the unit square with y up
+pub fn parse(source: &str, tolerance: f32) -> Result<Vec<Piece>, String> {
+    let mut styles = vec![Style {
+        fill: Some(Vec4::new(0.0, 0.0, 0.0, 1.0)),
+        stroke: None,
+        stroke_width: 1.0,
+        transform: IDENTITY,
+    }];
+    let mut size = 100.0;
+    let mut pieces = Vec::new();
+    let mut rest = source;
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+        // skip comments, doctypes and processing instructions
+        if let Some(comment) = rest.strip_prefix("!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let end = rest.find('>').ok_or("Unterminated tag")?;
+        let tag = &rest[..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with('!') || tag.starts_with('?') {
+            continue;
+        }
+        if let Some(closing) = tag.strip_prefix('/') {
+            if closing.trim() == "g" && styles.len() > 1 {
+                styles.pop();
+            }
+            continue;
+        }
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let (name, attribute_text) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        // definitions are only drawn where they are referenced, which isn't supported
+        if !self_closing && ["defs", "clipPath", "mask", "symbol", "pattern", "linearGradient", "radialGradient"].contains(&name) {
+            rest = rest.find(&format!("</{name}")).map_or("", |end| &rest[end..]);
+            continue;
+        }
+        let attributes = attributes(attribute_text)?;
+        let attribute = |name: &str| attributes.iter().rev().find(|(n, _)| n == name).map(|(_, value)| value.clone());
+
+        let parent = styles[styles.len() - 1].clone();
+        let mut style = parent.clone();
+        if let Some(fill) = attribute("fill") {
+            style.fill = parse_color(&fill)?;
+        }
+        if let Some(stroke) = attribute("stroke") {
+            style.stroke = parse_color(&stroke)?;
+        }
+        if let Some(width) = attribute("stroke-width") {
+            style.stroke_width = width.trim().trim_end_matches("px").parse().map_err(|_| format!("Invalid stroke-width \"{width}\""))?;
+        }
+        if let Some(transform) = attribute("transform") {
+            style.transform = compose(&parent.transform, &parse_transform(&transform)?);
+        }
+        match name {
+            "svg" => {
+                // the tolerance is a fraction of the view box, or of the size if there is none
+                let view_box = attribute("viewBox").map(|v| numbers(&v)).transpose()?;
+                let dimension = |name: &str| attribute(name).and_then(|v| v.trim().trim_end_matches("px").parse::<f32>().ok());
+                size = match (view_box.as_deref(), dimension("width"), dimension("height")) {
+                    (Some([_, _, w, h]), _, _) => w.max(*h),
+                    (_, Some(w), Some(h)) => w.max(h),
+                    _ => size,
+                };
+            }
+            "g" if !self_closing => styles.push(style),
+            _ => {
+                let local_tolerance = tolerance * size / scale(&style.transform);
+                let outlines = element_outlines(name, &attribute, local_tolerance)?
+                    .into_iter()
+                    .map(|(points, closed)| (points.iter().map(|p| apply(&style.transform, *p)).collect::<Vec<_>>(), closed))
+                    .filter(|(points, _)| points.len() >= 2)
+                    .collect::<Vec<_>>();
+                // fills close every subpath, but lines have nothing to fill
+                let filled = outlines
+                    .iter()
+                    .map(|(points, _)| {
+                        let mut points = points.clone();
+                        points.dedup();
+                        if points.len() > 1 && points.first() == points.last() {
+                            points.pop();
+                        }
+                        points
+                    })
+                    .filter(|points| points.len() >= 3)
+                    .collect::<Vec<_>>();
+                if let Some(color) = style.fill
+                    && !filled.is_empty()
+                    && name != "line"
+                    && name != "polyline"
+                {
+                    pieces.push(fill(&filled, color).ok_or(format!("Couldn't fill a <{name}>, its outline crosses itself"))?);
+                }
+                if let Some(color) = style.stroke {
+                    let width = style.stroke_width * scale(&style.transform);
+                    pieces.extend(outlines.iter().map(|(points, closed)| stroke(points, *closed, width, color)));
+                }
+            }
+        }
+    }
+    if pieces.is_empty() {
+        return Err("The file contains no shapes".into());
+    }
+    // flip y and fit everything into the unit square around the origin
+    let all = pieces.iter().flat_map(|piece| piece.points.iter());
+    let (min, max) = all.fold((Vec2::broadcast(f32::INFINITY), Vec2::broadcast(f32::NEG_INFINITY)), |(min, max), p| {
+        (Vec2::partial_min(min, *p), Vec2::partial_max(max, *p))
+    });
+    let center = (min + max) / 2.0;
+    let extent = (max - min).reduce_partial_max().max(f32::EPSILON);
+    for point in pieces.iter_mut().flat_map(|piece| piece.points.iter_mut()) {
+        *point = (*point - center) / extent * Vec2::new(1.0, -1.0);
+    }
+    Ok(pieces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_maps(transform: &str, from: (f32, f32), to: (f32, f32)) {
+        let mapped = apply(&parse_transform(transform).unwrap(), Vec2::new(from.0, from.1));
+        assert!(mapped.distance(Vec2::new(to.0, to.1)) < 1e-5, "\"{transform}\" maps {from:?} to {mapped:?} instead of {to:?}");
+    }
+
+    #[test]
+    fn transform_lists() {
+        assert_maps("", (3.0, 4.0), (3.0, 4.0));
+        assert_maps("translate(10 20)", (1.0, 1.0), (11.0, 21.0));
+        assert_maps("translate(5)", (1.0, 1.0), (6.0, 1.0));
+        // the rightmost transform applies first
+        assert_maps("translate(10, 20) rotate(90)", (1.0, 0.0), (10.0, 21.0));
+        assert_maps("rotate(90),translate(10,20)", (1.0, 0.0), (-20.0, 11.0));
+        assert_maps("scale(2) scale(1, 3)", (1.0, 1.0), (2.0, 6.0));
+        assert_maps("rotate(90 1 1)", (2.0, 1.0), (1.0, 2.0));
+        assert_maps("skewX(45)", (0.0, 1.0), (1.0, 1.0));
+        assert_maps("skewY(45)", (1.0, 0.0), (1.0, 1.0));
+        assert_maps("matrix(1 2 3 4 5 6)", (1.0, 1.0), (9.0, 12.0));
+        assert_maps("matrix(1,0,0,1,-1.5.5)", (0.0, 0.0), (-1.5, 0.5));
+
+        for invalid in ["translate(1 2 3)", "rotate(1 2)", "skew(3)", "scale", "matrix(1 2 3 4 5)", "translate(1 x)"] {
+            assert!(parse_transform(invalid).is_err(), "\"{invalid}\" parsed");
+        }
+    }
+
+    #[test]
+    fn colours() {
+        let red = Some(Vec4::new(1.0, 0.0, 0.0, 1.0));
+        for value in ["#f00", "#F00", "#ff0000", " #FF0000 ", "red", "RED", "rgb(255, 0, 0)", "rgb(100%, 0%, 0%)"] {
+            assert_eq!(parse_color(value), Ok(red), "{value}");
+        }
+        assert_eq!(parse_color("#fff"), Ok(Some(Vec4::one())));
+        assert_eq!(parse_color("black"), Ok(Some(Vec4::new(0.0, 0.0, 0.0, 1.0))));
+        assert_eq!(parse_color("none"), Ok(None));
+        assert_eq!(parse_color("transparent"), Ok(None));
+        // channels are converted from srgb
+        let gray = parse_color("#808080").unwrap().unwrap();
+        assert!((gray.x - 0.2158605).abs() < 1e-5);
+
+        for invalid in ["#", "#ff", "#12345", "#ggg", "#12345g", "rgb(1, 2)", "rgb(1, 2, x)", "bluish"] {
+            assert!(parse_color(invalid).is_err(), "\"{invalid}\" parsed");
+        }
+    }
+
+    #[test]
+    fn non_ascii_colours_are_rejected() {
+        // byte lengths of 3 and 6, which used to be sliced through the middle of a character
+        for invalid in ["#aé", "#abcdé", "#€", "#ab€c"] {
+            assert!(parse_color(invalid).is_err(), "\"{invalid}\" parsed");
+        }
+    }
+}
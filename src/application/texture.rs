@@ -0,0 +1,119 @@
+use super::files::{FilePicker, PickedFile};
+
+const DEFAULT_SIZE: u32 = 256;
+const CHECKER_CELLS: u32 = 8;
+// the default limits of wgpu
+const MAX_SIZE: u32 = 8192;
+
+// rgba8 pixels, top row first
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+// a checkerboard with a red "F" on it, which looks different under every flip and rotation
+pub fn default_texture() -> Image {
+    let cell = DEFAULT_SIZE / CHECKER_CELLS;
+    let in_f = |x: u32, y: u32| {
+        let (x, y) = (x / cell, y / cell);
+        let stem = x == 2 && (1..7).contains(&y);
+        let top = y == 1 && (2..6).contains(&x);
+        let middle = y == 3 && (2..5).contains(&x);
+        stem || top || middle
+    };
+    let mut rgba = Vec::with_capacity((4 * DEFAULT_SIZE * DEFAULT_SIZE) as usize);
+    for y in 0..DEFAULT_SIZE {
+        for x in 0..DEFAULT_SIZE {
+            let pixel = if in_f(x, y) {
+                [220, 40, 40, 255]
+            } else if (x / cell + y / cell).is_multiple_of(2) {
+                [200, 200, 200, 255]
+            } else {
+                [90, 90, 90, 255]
+            };
+            rgba.extend(pixel);
+        }
+    }
+    Image { width: DEFAULT_SIZE, height: DEFAULT_SIZE, rgba }
+}
+
+// png or jpeg, scaled down if the gpu couldn't hold it
+pub fn decode(bytes: &[u8]) -> Result<Image, String> {
+    let image = image::load_from_memory(bytes).map_err(|e| format!("Couldn't decode the image: {e}"))?;
+    Ok(fit(image))
+}
+
+// rgba8 pixels of a decoded image, scaled down if the gpu couldn't hold it
+pub fn fit(mut image: image::DynamicImage) -> Image {
+    if image.width() > MAX_SIZE || image.height() > MAX_SIZE {
+        image = image.resize(MAX_SIZE, MAX_SIZE, image::imageops::FilterType::Triangle);
+    }
+    let rgba = image.to_rgba8();
+    Image { width: rgba.width(), height: rgba.height(), rgba: rgba.into_raw() }
+}
+
+// an image drawn on the model instead of its colours
+pub struct ModelTexture {
+    pub enabled: bool, // drawn on the model
+    pub show_window: bool,
+    picker: FilePicker,
+    name: String,
+    error: Option<String>,
+}
+
+impl ModelTexture {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            show_window: false,
+            picker: FilePicker::new(&["png", "jpg", "jpeg"]),
+            name: "Default".into(),
+            error: None,
+        }
+    }
+
+    // mirrors the Texturing uniform block in default_glsl.frag
+    pub fn data(&self) -> [f32; 4] {
+        [if self.enabled { 1.0 } else { 0.0 }, 0.0, 0.0, 0.0]
+    }
+
+    // a texture that came with the model replaces the current one and is turned on
+    pub fn use_model_texture(&mut self, name: String) {
+        self.name = name;
+        self.error = None;
+        self.enabled = true;
+    }
+
+    fn load(&mut self, file: Result<PickedFile, String>) -> Option<Image> {
+        self.error = None;
+        match file.and_then(|file| decode(&file.bytes).map(|image| (file.name, image))) {
+            Ok((name, image)) => {
+                self.name = name;
+                Some(image)
+            }
+            Err(e) => {
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+
+    // returns an image when it should replace the current texture
+    pub fn gui(&mut self, ui: &mut egui::Ui) -> Option<Image> {
+        ui.checkbox(&mut self.enabled, "Show on the model");
+        let mut image = self.picker.gui(ui).and_then(|file| self.load(file));
+        ui.horizontal(|ui| {
+            ui.label(format!("Showing {}", self.name));
+            if ui.button("Use default").clicked() {
+                self.name = "Default".into();
+                self.error = None;
+                image = Some(default_texture());
+            }
+        });
+        if let Some(e) = &self.error {
+            ui.colored_label(ui.visuals().warn_fg_color, e);
+        }
+        image
+    }
+}
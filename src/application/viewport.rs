@@ -0,0 +1,96 @@
+use vek::Vec2;
+
+use super::camera::Camera;
+
+const DIVIDER_WIDTH: f32 = 2.0; // in physical pixels
+
+// a region of the window, in physical pixels
+#[derive(Clone, Copy)]
+pub struct Viewport {
+    pub origin: Vec2<f32>,
+    pub size: Vec2<f32>,
+}
+
+impl Viewport {
+    pub fn full(window_size: Vec2<f32>) -> Self {
+        Self {
+            origin: Vec2::zero(),
+            size: window_size,
+        }
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.size.x / self.size.y
+    }
+
+    pub fn contains(&self, screen: Vec2<f32>) -> bool {
+        let local = screen - self.origin;
+        local.x >= 0.0 && local.y >= 0.0 && local.x < self.size.x && local.y < self.size.y
+    }
+
+    // position relative to the top left corner of the viewport
+    pub fn to_local(self, screen: Vec2<f32>) -> Vec2<f32> {
+        screen - self.origin
+    }
+}
+
+// shows the untransformed model next to the transformed one
+pub struct SplitView {
+    pub enabled: bool,
+    pub linked: bool, // both views share the main camera
+    before_camera: Camera,
+    grabbed_before: bool, // the cursor is over the "before" view, or a drag started there
+}
+
+impl SplitView {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            linked: true,
+            before_camera: Camera::new(),
+            grabbed_before: false,
+        }
+    }
+
+    // "before" view on the left if the split is enabled, and the view showing the transformed model
+    pub fn layout(&self, window_size: Vec2<f32>) -> (Option<Viewport>, Viewport) {
+        if !self.enabled {
+            return (None, Viewport::full(window_size));
+        }
+        let width = ((window_size.x - DIVIDER_WIDTH) / 2.0).floor().max(1.0);
+        let before = Viewport {
+            origin: Vec2::zero(),
+            size: Vec2::new(width, window_size.y),
+        };
+        let after = Viewport {
+            origin: Vec2::new(width + DIVIDER_WIDTH, 0.0),
+            size: Vec2::new((window_size.x - width - DIVIDER_WIDTH).max(1.0), window_size.y),
+        };
+        (Some(before), after)
+    }
+
+    pub fn before_camera<'a>(&'a self, main_camera: &'a Camera) -> &'a Camera {
+        if self.linked { main_camera } else { &self.before_camera }
+    }
+
+    // the camera that pans and zooms with the cursor. while a button is held the view the drag started in keeps it
+    pub fn camera_under_cursor<'a>(&'a mut self, main_camera: &'a mut Camera, in_before: bool, clicking: bool) -> &'a mut Camera {
+        if !clicking {
+            self.grabbed_before = in_before;
+        }
+        if self.enabled && !self.linked && self.grabbed_before {
+            &mut self.before_camera
+        } else {
+            main_camera
+        }
+    }
+
+    pub fn gui(&mut self, ui: &mut egui::Ui, main_camera: &Camera) {
+        ui.checkbox(&mut self.enabled, "Split before/after view");
+        if self.enabled && ui.checkbox(&mut self.linked, "Link cameras").changed() && !self.linked {
+            // start out where the shared camera was
+            self.before_camera.position = main_camera.position;
+            self.before_camera.zoom = main_camera.zoom;
+        }
+    }
+}
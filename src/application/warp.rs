@@ -0,0 +1,144 @@
+use egui::{Color32, Stroke};
+use vek::{Vec2, Vec4};
+
+use super::overlay::Overlay;
+use crate::renderer::Vertex;
+
+// points mapped further out than this are treated as sent to infinity
+const FAR: f32 = 1e3;
+const GRID_COLOR: Color32 = Color32::from_rgb(120, 160, 255);
+
+fn is_far(p: Vec2<f32>) -> bool {
+    !p.x.is_finite() || !p.y.is_finite() || p.magnitude() > FAR
+}
+
+// move every vertex through the map, collapsing triangles that touch infinity
+pub fn warp_mesh(vertices: &[Vertex], indices: &[u32], map: impl Fn(Vec2<f32>) -> Vec2<f32>) -> (Vec<Vertex>, Vec<u32>) {
+    let mut far = vec![false; vertices.len()];
+    let warped = vertices
+        .iter()
+        .zip(far.iter_mut())
+        .map(|(vertex, far)| {
+            let p = map(vertex.position.xy());
+            *far = is_far(p);
+            let p = if *far { Vec2::zero() } else { p };
+            Vertex { position: Vec4::new(p.x, p.y, vertex.position.z, 1.0), ..*vertex }
+        })
+        .collect();
+    let indices = indices
+        .chunks(3)
+        .flat_map(|triangle| {
+            if triangle.iter().any(|i| far[*i as usize]) {
+                [triangle[0]; 3]
+            } else {
+                [triangle[0], triangle[1], triangle[2]]
+            }
+        })
+        .collect();
+    (warped, indices)
+}
+
+// draw the image of a curve given by its samples, breaking it where it jumps through infinity
+pub fn draw_curve(overlay: &Overlay, points: impl Iterator<Item = Vec2<f32>>, stroke: Stroke) {
+    let max_jump = overlay.painter.clip_rect().size().max_elem() / 2.0;
+    let mut line: Vec<egui::Pos2> = Vec::new();
+    for p in points {
+        let screen = (!is_far(p)).then(|| overlay.to_screen(p));
+        match (screen, line.last()) {
+            (Some(screen), Some(last)) if last.distance(screen) < max_jump => line.push(screen),
+            (screen, _) => {
+                if line.len() > 1 {
+                    overlay.painter.line(std::mem::take(&mut line), stroke);
+                }
+                line.clear();
+                line.extend(screen);
+            }
+        }
+    }
+    if line.len() > 1 {
+        overlay.painter.line(line, stroke);
+    }
+}
+
+// the grid lines of the plane before the stack is applied, drawn where the stack sends them
+pub struct WarpedGrid {
+    pub enabled: bool,
+    extent: f32,
+    spacing: f32,
+}
+
+impl WarpedGrid {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            extent: 2.0,
+            spacing: 0.25,
+        }
+    }
+
+    pub fn gui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.enabled, "Warped grid");
+        if self.enabled {
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut self.extent).speed(0.05).range(0.1..=20.0).prefix("extent: "));
+                ui.add(egui::DragValue::new(&mut self.spacing).speed(0.01).range(0.05..=5.0).prefix("spacing: "));
+            });
+        }
+    }
+
+    pub fn draw(&self, overlay: &Overlay, map: impl Fn(Vec2<f32>) -> Vec2<f32>) {
+        if !self.enabled {
+            return;
+        }
+        const SAMPLES: usize = 200;
+        let lines = (self.extent / self.spacing).floor() as i32;
+        for k in -lines..=lines {
+            let c = k as f32 * self.spacing;
+            let stroke = Stroke::new(if k == 0 { 1.5 } else { 0.75 }, GRID_COLOR.gamma_multiply(if k == 0 { 1.0 } else { 0.6 }));
+            let t = |s: usize| -self.extent + 2.0 * self.extent * s as f32 / SAMPLES as f32;
+            draw_curve(overlay, (0..=SAMPLES).map(|s| map(Vec2::new(c, t(s)))), stroke);
+            draw_curve(overlay, (0..=SAMPLES).map(|s| map(Vec2::new(t(s), c))), stroke);
+        }
+    }
+}
+
+// derivative of the map at p by central differences, as rows [[∂x'/∂x, ∂x'/∂y], [∂y'/∂x, ∂y'/∂y]]
+pub fn jacobian(map: impl Fn(Vec2<f32>) -> Vec2<f32>, p: Vec2<f32>) -> [[f32; 2]; 2] {
+    let h = 1e-3 * p.magnitude().max(1.0);
+    let dx = (map(p + Vec2::new(h, 0.0)) - map(p - Vec2::new(h, 0.0))) / (2.0 * h);
+    let dy = (map(p + Vec2::new(0.0, h)) - map(p - Vec2::new(0.0, h))) / (2.0 * h);
+    [[dx.x, dy.x], [dx.y, dy.y]]
+}
+
+// a small square around p, where the map sends it, and where its linearisation at p sends it
+pub fn draw_linearisation(overlay: &Overlay, map: impl Fn(Vec2<f32>) -> Vec2<f32>, p: Vec2<f32>) {
+    const SAMPLES: usize = 16;
+    let image = map(p);
+    if is_far(image) {
+        return;
+    }
+    let j = jacobian(&map, p);
+    let linear = |q: Vec2<f32>| {
+        let d = q - p;
+        image + Vec2::new(j[0][0] * d.x + j[0][1] * d.y, j[1][0] * d.x + j[1][1] * d.y)
+    };
+    let (min, max) = overlay.visible_world();
+    let half = (max - min).y * 0.03;
+    let corners = [Vec2::new(-half, -half), Vec2::new(half, -half), Vec2::new(half, half), Vec2::new(-half, half)];
+    // walk the outline of the square, finely so the curved image shows
+    let outline = (0..4).flat_map(|side| {
+        let (a, b) = (corners[side], corners[(side + 1) % 4]);
+        (0..=SAMPLES).map(move |i| p + a + (b - a) * (i as f32 / SAMPLES as f32))
+    });
+    draw_curve(overlay, outline.clone(), Stroke::new(1.0, Color32::GRAY));
+    draw_curve(overlay, outline.clone().map(&map), Stroke::new(2.0, Color32::from_rgb(255, 200, 80)));
+    draw_curve(overlay, outline.map(linear), Stroke::new(1.5, Color32::from_rgb(80, 220, 255)));
+
+    let text = format!(
+        "J = [{:.3} {:.3}; {:.3} {:.3}]\ndet J = {:.3}",
+        j[0][0], j[0][1], j[1][0], j[1][1],
+        j[0][0] * j[1][1] - j[0][1] * j[1][0],
+    );
+    let anchor = overlay.to_screen(image + Vec2::new(half, half) * 1.5);
+    overlay.painter.text(anchor, egui::Align2::LEFT_BOTTOM, text, egui::FontId::monospace(12.0), Color32::WHITE);
+}
@@ -3,6 +3,8 @@ mod pass;
 mod pipeline;
 mod renderable;
 mod renderer;
+mod texture;
 pub use self::renderable::Vertex;
+pub use self::renderable::PointInstance;
 pub use self::renderable::Renderable;
-pub use self::renderer::Renderer;
+pub use self::renderer::{object_slots, Renderer, View};
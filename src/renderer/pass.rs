@@ -1,15 +1,17 @@
-use std::sync::Arc;
+use std::{num::NonZeroU64, sync::Arc};
 
 use log::debug;
 use wgpu::{
     include_spirv, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, BindingType, BlendState, Buffer, BufferBindingType, BufferUsages,
-    ColorWrites, Device, RenderPass, ShaderStages, SurfaceConfiguration,
+    ColorWrites, Device, PrimitiveTopology, RenderPass, ShaderStages, SurfaceConfiguration,
 };
 
 use super::{
     pipeline::{Pipeline, PipelineBuilder},
-    renderable::Renderable,
+    renderable::{PointInstance, Renderable, Vertex},
+    renderer::OBJECT_SLOT_SIZE,
+    texture::GlobalTexture,
 };
 
 macro_rules! include_shader {
@@ -17,14 +19,35 @@ macro_rules! include_shader {
         include_spirv!(concat!(env!("OUT_DIR"), $file))
     };
 }
+// which constructor made a pass, so it can be rebuilt when the global bindings change
+#[derive(Clone, Copy)]
+pub enum PassKind {
+    Default,
+    Grid,
+    Ifs,
+    Points,
+}
+
+// everything the passes bind in group 0
+pub struct GlobalBindings<'a> {
+    pub buffers: &'a Vec<Buffer>,
+    pub object_buffer: Option<usize>, // bound at a dynamic offset, one slot per object
+    pub textures: &'a [GlobalTexture],
+}
+
 pub struct DefaultPass {
+    pub kind: PassKind,
     pipeline: Pipeline,
     bind_groups: Vec<BindGroup>,
+    dynamic: bool, // whether the bind group takes the offset of an object slot
 }
 
 
-fn make_entries(buffers: &Vec<Buffer>) -> (Vec<BindGroupLayoutEntry>, Vec<BindGroupEntry<'_>>) {
-    let layout_entries = buffers
+// buffers are bound at their index, textures at the bindings they were created with
+fn make_entries<'a>(bindings: &GlobalBindings<'a>) -> (Vec<BindGroupLayoutEntry>, Vec<BindGroupEntry<'a>>) {
+    let GlobalBindings { buffers, object_buffer, textures } = *bindings;
+    let slot_size = NonZeroU64::new(OBJECT_SLOT_SIZE);
+    let mut layout_entries = buffers
         .iter()
         .enumerate()
         .map(|(i, buf)| {
@@ -37,42 +60,69 @@ fn make_entries(buffers: &Vec<Buffer>) -> (Vec<BindGroupLayoutEntry>, Vec<BindGr
                     } else {
                         unimplemented!("deal with storage buffers in bindgroupLayoutEntry")
                     },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
+                    has_dynamic_offset: object_buffer == Some(i),
+                    min_binding_size: if object_buffer == Some(i) { slot_size } else { None },
                 },
                 count: None,
             }
         })
         .collect::<Vec<_>>();
-    let bindgroup_entries = buffers
+    let mut bindgroup_entries = buffers
         .iter()
         .enumerate()
         .map(|(i, buf)| BindGroupEntry {
             binding: i as u32,
-            resource: buf.as_entire_binding(),
+            resource: if object_buffer == Some(i) {
+                // only one slot is visible at a time
+                wgpu::BindingResource::Buffer(wgpu::BufferBinding { buffer: buf, offset: 0, size: slot_size })
+            } else {
+                buf.as_entire_binding()
+            },
         })
         .collect::<Vec<_>>();
+    for texture in textures {
+        layout_entries.extend(texture.layout_entries());
+        bindgroup_entries.extend(texture.bind_group_entries());
+    }
     (layout_entries, bindgroup_entries)
 }
 
 pub fn draw_grid<'a, 'b>(render_pass: &mut RenderPass<'b>, grid: &'a DefaultPass) 
 where 'a: 'b {
     render_pass.set_pipeline(&grid.pipeline.pipeline);
-    for (i, group) in grid.bind_groups.iter().enumerate() {
-        render_pass.set_bind_group(i as u32, group, &[]);
-    }
+    grid.set_bind_groups(render_pass, 0);
     render_pass.draw(0..3,0..1);
 }
 
+pub fn draw_ifs<'a, 'b>(render_pass: &mut RenderPass<'b>, ifs: &'a DefaultPass, point_count: u32)
+where 'a: 'b {
+    render_pass.set_pipeline(&ifs.pipeline.pipeline);
+    ifs.set_bind_groups(render_pass, 0);
+    render_pass.draw(0..point_count, 0..1);
+}
+
 impl DefaultPass {
+    pub fn with_kind(
+        kind: PassKind,
+        device: &Device,
+        config: &SurfaceConfiguration,
+        bindings: &GlobalBindings,
+    ) -> Self {
+        match kind {
+            PassKind::Default => Self::new(device, config, bindings),
+            PassKind::Grid => Self::new_grid(device, config, bindings),
+            PassKind::Ifs => Self::new_ifs(device, config, bindings),
+            PassKind::Points => Self::new_points(device, config, bindings),
+        }
+    }
 
     pub fn new_grid(
         device: &Device,
         config: &SurfaceConfiguration,
-        buffers: &Vec<Buffer>,
+        bindings: &GlobalBindings,
     ) -> Self {
         let mut bind_groups = Vec::new();
-        let (layout_entries, bindgroup_entries) = make_entries(buffers);
+        let (layout_entries, bindgroup_entries) = make_entries(bindings);
         
         let vertex_shader_spv = device.create_shader_module(include_shader!("/shaders/fullscreen_tri.vert.spv"));
         let vtx_entry_point = Some("main");
@@ -98,18 +148,99 @@ impl DefaultPass {
         });
         bind_groups.push(bind_group);
         Self {
+            kind: PassKind::Grid,
+            pipeline,
+            bind_groups,
+            dynamic: bindings.object_buffer.is_some(),
+        }
+    }
+
+    pub fn new_ifs(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        bindings: &GlobalBindings,
+    ) -> Self {
+        let mut bind_groups = Vec::new();
+        let (layout_entries, bindgroup_entries) = make_entries(bindings);
+
+        let vertex_shader_spv = device.create_shader_module(include_shader!("/shaders/ifs.vert.spv"));
+        let fragment_shader_spv = device.create_shader_module(include_shader!("/shaders/ifs.frag.spv"));
+        let bindgroup_layout = device.create_bind_group_layout(
+            &BindGroupLayoutDescriptor { label: Some("IFS bindgroup layout"), entries: &layout_entries }
+        );
+        // points are generated in the vertex shader, so no vertex buffer
+        let pipeline = PipelineBuilder::new(&vertex_shader_spv, Some("main"), false)
+            .with_fragment_shader(&fragment_shader_spv, Some("main"))
+            .with_topology(PrimitiveTopology::PointList)
+            .add_fragment_target(Some(wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(BlendState::REPLACE),
+                write_mask: ColorWrites::ALL,
+            }))
+            .add_bind_group_layout(&bindgroup_layout)
+            .build(device);
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("IFS bind group"),
+            layout: &bindgroup_layout,
+            entries: &bindgroup_entries,
+        });
+        bind_groups.push(bind_group);
+        Self {
+            kind: PassKind::Ifs,
             pipeline,
             bind_groups,
+            dynamic: bindings.object_buffer.is_some(),
+        }
+    }
+
+    // camera facing sprites, one instance per point
+    pub fn new_points(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        bindings: &GlobalBindings,
+    ) -> Self {
+        let mut bind_groups = Vec::new();
+        let (layout_entries, bindgroup_entries) = make_entries(bindings);
+
+        let vertex_shader_spv = device.create_shader_module(include_shader!("/shaders/points.vert.spv"));
+        let fragment_shader_spv = device.create_shader_module(include_shader!("/shaders/points.frag.spv"));
+        let bindgroup_layout = device.create_bind_group_layout(
+            &BindGroupLayoutDescriptor { label: Some("Points bindgroup layout"), entries: &layout_entries }
+        );
+        const VERTEX_BUFFERS: [wgpu::VertexBufferLayout; 2] = [Vertex::desc(), PointInstance::desc()];
+        let pipeline = PipelineBuilder::new(&vertex_shader_spv, Some("main"), true)
+            .with_vertex_buffers(&VERTEX_BUFFERS)
+            .with_fragment_shader(&fragment_shader_spv, Some("main"))
+            .add_fragment_target(Some(wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(BlendState::REPLACE),
+                write_mask: ColorWrites::ALL,
+            }))
+            .add_bind_group_layout(&bindgroup_layout)
+            .build(device);
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Points bind group"),
+            layout: &bindgroup_layout,
+            entries: &bindgroup_entries,
+        });
+        bind_groups.push(bind_group);
+        Self {
+            kind: PassKind::Points,
+            pipeline,
+            bind_groups,
+            dynamic: bindings.object_buffer.is_some(),
         }
     }
 
     pub fn new(
         device: &Device,
         config: &SurfaceConfiguration,
-        buffers: &Vec<Buffer>,
+        bindings: &GlobalBindings,
     ) -> Self {
         let mut bind_groups = Vec::new();
-        let (layout_entries, bindgroup_entries) = make_entries(buffers);
+        let (layout_entries, bindgroup_entries) = make_entries(bindings);
         // TODO: see if this works in web
         let v_shader_spv =
             device.create_shader_module(include_shader!("/shaders/default_glsl.vert.spv"));
@@ -155,8 +286,10 @@ impl DefaultPass {
         bind_groups.push(default_bind_group);
         debug!("Default pipeline constructed");
         Self {
+            kind: PassKind::Default,
             pipeline,
             bind_groups,
+            dynamic: bindings.object_buffer.is_some(),
         }
     }
 
@@ -170,15 +303,20 @@ impl DefaultPass {
     {
         // 1. set the pipeline
         render_pass.set_pipeline(&self.pipeline.pipeline);
-        // 2. set the bind groups
-        for (i, group) in self.bind_groups.iter().enumerate() {
-            render_pass.set_bind_group(i as u32, group, &[]);
-        }
-        // 3. draw the renderables
         for renderable in renderables {
+            // 2. set the bind groups, at the slot of the renderable's object
+            self.set_bind_groups(render_pass, renderable.object);
+            // 3. draw the renderable
             renderable.draw(render_pass);
         }
 
         Ok(())
     }
+
+    fn set_bind_groups(&self, render_pass: &mut RenderPass, object: u32) {
+        let offsets = [object * OBJECT_SLOT_SIZE as u32];
+        for (i, group) in self.bind_groups.iter().enumerate() {
+            render_pass.set_bind_group(i as u32, group, &offsets[..self.dynamic as usize]);
+        }
+    }
 }
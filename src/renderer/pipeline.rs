@@ -61,6 +61,11 @@ impl<'a> PipelineBuilder<'a> {
         }
     }
 
+    pub fn with_vertex_buffers(&mut self, layouts: &'a [VertexBufferLayout<'a>]) -> &mut Self {
+        self.render_pipeline_descriptor.vertex.buffers = layouts;
+        self
+    }
+
     pub fn with_fragment_shader(
         &mut self,
         shader_module: &'a ShaderModule,
@@ -91,6 +96,11 @@ impl<'a> PipelineBuilder<'a> {
         self
     }
 
+    pub fn with_topology(&mut self, topology: PrimitiveTopology) -> &mut Self {
+        self.render_pipeline_descriptor.primitive.topology = topology;
+        self
+    }
+
     pub fn with_cull_mode(&mut self, cull_mode: Face) -> &mut Self {
         self.render_pipeline_descriptor.primitive.cull_mode = Some(cull_mode);
         self
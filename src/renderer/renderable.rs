@@ -1,21 +1,22 @@
 use bytemuck::{Pod, Zeroable};
-use vek::Vec4;
+use vek::{Vec3, Vec4};
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
-    BufferUsages, Device, RenderPass,
+    BufferUsages, Device, IndexFormat, Queue, RenderPass,
 };
 
 #[repr(C)]
-#[derive(Copy, Clone, Zeroable, Pod)]
+#[derive(Copy, Clone, PartialEq, Zeroable, Pod)]
 pub struct Vertex {
     pub position: Vec4<f32>,
     pub uv: Vec4<f32>,
+    pub color: Vec4<f32>, // replaces the corner colours unless alpha is 0
 }
 
 impl Vertex {
     pub const fn desc() -> wgpu::VertexBufferLayout<'static> {
-        const ATTRIBS: [wgpu::VertexAttribute; 2] =
-            wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4];
+        const ATTRIBS: [wgpu::VertexAttribute; 3] =
+            wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4];
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
             step_mode: wgpu::VertexStepMode::Vertex,
@@ -26,40 +27,118 @@ impl Vertex {
         Self {
             position,
             uv,
+            color: Vec4::zero(),
+        }
+    }
+    pub fn with_color(self, color: Vec4<f32>) -> Self {
+        Self { color, ..self }
+    }
+}
+
+// one copy of an instanced mesh, like the sprite of a point in a point cloud
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Zeroable, Pod)]
+pub struct PointInstance {
+    pub position: Vec3<f32>,
+    pub radius: f32,
+    pub color: Vec4<f32>,
+}
+
+impl PointInstance {
+    pub const fn desc() -> wgpu::VertexBufferLayout<'static> {
+        // after the attributes of Vertex, which is in the first vertex buffer
+        const ATTRIBS: [wgpu::VertexAttribute; 3] =
+            wgpu::vertex_attr_array![3 => Float32x3, 4 => Float32, 5 => Float32x4];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<PointInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBS,
         }
     }
 }
 
 pub struct Renderable {
     pub pass_name: String,
+    pub object: u32, // slot of the per object buffer it is drawn with
     vtx_buffer: wgpu::Buffer,
     idx_buffer: wgpu::Buffer,
+    index_format: IndexFormat,
     num_indices: u32,
+    instances: Option<(wgpu::Buffer, u32)>, // drawn once per instance when set
+}
+
+// small meshes keep 16 bit indices, larger ones need 32 bits.
+// buffer writes have to be a multiple of 4 bytes, so an odd number of 16 bit indices gets a zero appended
+fn index_bytes(indices: &[u32], format: IndexFormat) -> Vec<u8> {
+    match format {
+        IndexFormat::Uint16 => {
+            let mut indices = indices.iter().map(|i| *i as u16).collect::<Vec<_>>();
+            if indices.len() % 2 == 1 {
+                indices.push(0);
+            }
+            bytemuck::cast_slice(&indices).to_vec()
+        }
+        IndexFormat::Uint32 => bytemuck::cast_slice(indices).to_vec(),
+    }
 }
 
 impl Renderable {
-    pub fn new(device: &Device, vertices: &[Vertex], indices: &[u16], pass_name: String) -> Self {
+    pub fn new(device: &Device, vertices: &[Vertex], indices: &[u32], pass_name: String, object: u32) -> Self {
         let num_indices = indices.len() as u32;
+        let index_format = if indices.iter().all(|i| *i <= u16::MAX as u32) { IndexFormat::Uint16 } else { IndexFormat::Uint32 };
         let vtx_buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("VertexBuffer"),
             contents: bytemuck::cast_slice(vertices),
-            usage: BufferUsages::VERTEX,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
         });
         let idx_buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("IndexBuffer"),
-            contents: bytemuck::cast_slice(indices),
-            usage: BufferUsages::INDEX,
+            contents: &index_bytes(indices, index_format),
+            usage: BufferUsages::INDEX | BufferUsages::COPY_DST,
         });
         Self {
             pass_name,
+            object,
             vtx_buffer,
             idx_buffer,
+            index_format,
             num_indices,
+            instances: None,
+        }
+    }
+    pub fn new_instanced(device: &Device, vertices: &[Vertex], indices: &[u32], instances: &[PointInstance], pass_name: String, object: u32) -> Self {
+        let instance_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("InstanceBuffer"),
+            contents: bytemuck::cast_slice(instances),
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        });
+        Self {
+            instances: Some((instance_buffer, instances.len() as u32)),
+            ..Self::new(device, vertices, indices, pass_name, object)
+        }
+    }
+    // overwrite the contents, keeping the vertex and index count it was created with
+    pub fn write(&self, queue: &Queue, vertices: &[Vertex], indices: &[u32]) {
+        queue.write_buffer(&self.vtx_buffer, 0, bytemuck::cast_slice(vertices));
+        queue.write_buffer(&self.idx_buffer, 0, &index_bytes(indices, self.index_format));
+    }
+    // also keeps the instance count it was created with
+    pub fn write_instances(&self, queue: &Queue, instances: &[PointInstance]) {
+        if let Some((buffer, _)) = &self.instances {
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(instances));
         }
     }
     pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
         render_pass.set_vertex_buffer(0, self.vtx_buffer.slice(..));
-        render_pass.set_index_buffer(self.idx_buffer.slice(..), wgpu::IndexFormat::Uint16);
-        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+        render_pass.set_index_buffer(self.idx_buffer.slice(..), self.index_format);
+        match &self.instances {
+            // an empty buffer can't be bound
+            Some((_, 0)) => {}
+            Some((buffer, count)) => {
+                render_pass.set_vertex_buffer(1, buffer.slice(..));
+                render_pass.draw_indexed(0..self.num_indices, 0, 0..*count);
+            }
+            None => render_pass.draw_indexed(0..self.num_indices, 0, 0..1),
+        }
     }
 }
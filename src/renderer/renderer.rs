@@ -1,6 +1,7 @@
 use egui_wgpu::ScreenDescriptor;
 use log::{debug, warn};
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::{HashMap, HashSet}, sync::Arc};
+use vek::Vec2;
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt}, Buffer, BufferUsages, CommandEncoderDescriptor, Device, DeviceDescriptor, Extent3d, Instance, InstanceDescriptor, Origin3d, Queue, RenderPassColorAttachment, RenderPassDescriptor, RequestAdapterOptions, Surface, SurfaceConfiguration, TexelCopyTextureInfo, Texture, TextureDescriptor, TextureDimension, TextureUsages, TextureView, TextureViewDescriptor
 };
@@ -10,10 +11,33 @@ use crate::renderer::pass;
 
 use super::{
     gui_renderer::GuiRenderer,
-    pass::DefaultPass,
-    renderable::{Renderable, Vertex},
+    pass::{DefaultPass, GlobalBindings, PassKind},
+    renderable::{PointInstance, Renderable, Vertex},
+    texture::GlobalTexture,
 };
 
+// per object data is bound at a dynamic offset, the minimum alignment wgpu guarantees
+pub const OBJECT_SLOT_SIZE: u64 = 256;
+
+// lays out the data of each object in its own slot of an object buffer
+pub fn object_slots<'a>(slots: impl IntoIterator<Item = &'a [u8]>) -> Vec<u8> {
+    let mut data = Vec::new();
+    for slot in slots {
+        data.extend_from_slice(slot);
+        data.resize(data.len().next_multiple_of(OBJECT_SLOT_SIZE as usize), 0);
+    }
+    data
+}
+
+// a region of the window that is drawn with its own contents of some global buffers
+pub struct View {
+    pub origin: Vec2<f32>, // in physical pixels
+    pub size: Vec2<f32>,
+    pub buffers: Vec<(String, Vec<u8>)>, // global buffer name and the data it holds while drawing this view
+    pub hidden_passes: Vec<String>, // passes not drawn in this view, on top of the disabled ones
+    pub hidden_renderables: Vec<String>,
+}
+
 pub struct Renderer {
     _instance: Instance,
     pub window: Arc<Window>,
@@ -30,11 +54,21 @@ pub struct Renderer {
 
     renderables: HashMap<String, Arc<Renderable>>,
     passes: HashMap<String, DefaultPass>,
+    disabled_passes: HashSet<String>,
 
     grid_pass: Option<DefaultPass>,
+    ifs_pass: Option<DefaultPass>,
+    ifs_point_count: u32,
 
     global_buffers: Vec<Buffer>,
     buffer_index_map: HashMap<String, usize>,
+    object_buffer: Option<usize>,
+    global_textures: Vec<GlobalTexture>,
+    texture_index_map: HashMap<String, usize>,
+
+    views: Vec<View>,
+    // per view, staging buffers copied into global buffers (by index) before the view is drawn
+    view_buffers: Vec<Vec<(usize, Buffer)>>,
 }
 
 impl Renderer {
@@ -123,9 +157,17 @@ impl Renderer {
 
             renderables,
             grid_pass,
+            ifs_pass: None,
+            ifs_point_count: 0,
             passes,
+            disabled_passes: HashSet::new(),
             global_buffers: buffers,
             buffer_index_map: HashMap::new(),
+            object_buffer: None,
+            global_textures: Vec::new(),
+            texture_index_map: HashMap::new(),
+            views: Vec::new(),
+            view_buffers: Vec::new(),
         }
     }
 
@@ -142,34 +184,155 @@ impl Renderer {
         self.buffer_index_map.insert(name, idx);
         self.global_buffers.insert(idx, buffer);
     }
+
+    // a uniform buffer with a slot for each object, renderables are drawn with the slot of their object
+    pub fn add_object_buffer(&mut self, name: String, idx: usize, data: &[u8]) {
+        self.add_global_buffer(name, idx, data, BufferUsages::UNIFORM | BufferUsages::COPY_DST);
+        self.object_buffer = Some(idx);
+    }
+
+    // a buffer too small for the data is replaced by a bigger one
     pub fn write_buffer(&mut self, name: &str, data: &[u8]) {
-        if let Some(buffer) = self.global_buffers.get(*self.buffer_index_map.get(name).unwrap()) {
-            self.queue.write_buffer(buffer, 0, data);
+        let idx = *self.buffer_index_map.get(name).unwrap();
+        let Some(buffer) = self.global_buffers.get(idx) else {
+            return;
+        };
+        if (data.len() as u64) > buffer.size() {
+            let usage = buffer.usage();
+            self.global_buffers[idx] = self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(name),
+                size: (data.len() as u64).next_power_of_two(),
+                usage,
+                mapped_at_creation: false,
+            });
+            self.rebuild_passes();
+        }
+        self.queue.write_buffer(&self.global_buffers[idx], 0, data);
+    }
+
+    fn bindings(&self) -> GlobalBindings<'_> {
+        GlobalBindings {
+            buffers: &self.global_buffers,
+            object_buffer: self.object_buffer,
+            textures: &self.global_textures,
+        }
+    }
+
+    // passes bind the global buffers and textures they were made with
+    fn rebuild_passes(&mut self) {
+        let rebuilt = self
+            .passes
+            .iter()
+            .map(|(name, pass)| (name.clone(), DefaultPass::with_kind(pass.kind, &self.device, &self.config, &self.bindings())))
+            .collect();
+        self.passes = rebuilt;
+        if self.grid_pass.is_some() {
+            self.add_grid_pass();
+        }
+        if self.ifs_pass.is_some() {
+            self.add_ifs_pass();
+        }
+    }
+
+    // an rgba8 image bound to every pass added after it, at `binding` with its sampler at `binding + 1`
+    pub fn add_global_texture(&mut self, name: String, binding: u32, width: u32, height: u32, rgba: &[u8]) {
+        let texture = GlobalTexture::new(&self.device, &self.queue, &name, binding, width, height, rgba);
+        self.texture_index_map.insert(name, self.global_textures.len());
+        self.global_textures.push(texture);
+    }
+
+    pub fn write_texture(&mut self, name: &str, width: u32, height: u32, rgba: &[u8]) {
+        let Some(&idx) = self.texture_index_map.get(name) else {
+            warn!("global texture with name {} does not exist!", name);
+            return;
+        };
+        let texture = &self.global_textures[idx];
+        if texture.size() == (width, height) {
+            texture.write(&self.queue, rgba);
+            return;
+        }
+        // a new size needs a new texture, and every pass binds the old one
+        let binding = texture.binding;
+        self.global_textures[idx] = GlobalTexture::new(&self.device, &self.queue, name, binding, width, height, rgba);
+        self.rebuild_passes();
+    }
+
+    // split the window into views, or pass no views to draw the whole window with the global buffers as they are
+    pub fn set_views(&mut self, views: Vec<View>) {
+        self.view_buffers.resize_with(views.len(), Vec::new);
+        for (view, staging) in views.iter().zip(self.view_buffers.iter_mut()) {
+            let mut buffers = Vec::new();
+            for (name, data) in &view.buffers {
+                let Some(&idx) = self.buffer_index_map.get(name) else {
+                    warn!("view uses global buffer {} which does not exist!", name);
+                    continue;
+                };
+                // reuse last frame's staging buffer when it fits
+                let reusable = staging.iter().position(|(i, b)| *i == idx && b.size() == data.len() as u64);
+                let buffer = match reusable {
+                    Some(pos) => staging.swap_remove(pos).1,
+                    None => self.device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some(name.as_str()),
+                        size: data.len() as u64,
+                        usage: BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                    }),
+                };
+                self.queue.write_buffer(&buffer, 0, data);
+                buffers.push((idx, buffer));
+            }
+            *staging = buffers;
         }
+        self.views = views;
     }
 
     pub fn add_pass(&mut self, name: String) {
-        let pass = DefaultPass::new(&self.device, &self.config, &self.global_buffers);
+        let pass = DefaultPass::new(&self.device, &self.config, &self.bindings());
+        self.passes.insert(name, pass);
+    }
+
+    // draws instanced renderables as point sprites
+    pub fn add_points_pass(&mut self, name: String) {
+        let pass = DefaultPass::with_kind(PassKind::Points, &self.device, &self.config, &self.bindings());
         self.passes.insert(name, pass);
     }
 
     pub fn add_grid_pass(&mut self) {
-        self.grid_pass = Some(DefaultPass::new_grid(&self.device, &self.config,&self.global_buffers));
+        self.grid_pass = Some(DefaultPass::new_grid(&self.device, &self.config, &self.bindings()));
+    }
+
+    pub fn add_ifs_pass(&mut self) {
+        self.ifs_pass = Some(DefaultPass::new_ifs(&self.device, &self.config, &self.bindings()));
+    }
+
+    // number of points the ifs pass draws, 0 to skip it entirely
+    pub fn set_ifs_point_count(&mut self, count: u32) {
+        self.ifs_point_count = count;
+    }
+
+    pub fn set_pass_enabled(&mut self, name: &str, enabled: bool) {
+        if enabled {
+            self.disabled_passes.remove(name);
+        } else {
+            self.disabled_passes.insert(name.to_string());
+        }
     }
 
     pub fn add_renderable(
         &mut self,
         name: String,
         pipeline_tag: String,
-        indices: &[u16],
+        indices: &[u32],
         vertices: &[Vertex],
+        object: u32,
     ) -> Arc<Renderable>
     {
         debug!(
             "New renderable {} added, using pipeline {}",
             name, pipeline_tag
         );
-        let renderable = Arc::new(Renderable::new(&self.device, vertices, indices, pipeline_tag));
+        let renderable = Arc::new(Renderable::new(&self.device, vertices, indices, pipeline_tag, object));
+        // adding a renderable under an existing name replaces it
         self.renderables.insert(
             name,
             renderable.clone(),
@@ -177,6 +340,40 @@ impl Renderer {
         renderable
     }
 
+    pub fn add_instanced_renderable(
+        &mut self,
+        name: String,
+        pipeline_tag: String,
+        indices: &[u32],
+        vertices: &[Vertex],
+        instances: &[PointInstance],
+        object: u32,
+    ) -> Arc<Renderable>
+    {
+        debug!("New renderable {} added with {} instances, using pipeline {}", name, instances.len(), pipeline_tag);
+        let renderable = Arc::new(Renderable::new_instanced(&self.device, vertices, indices, instances, pipeline_tag, object));
+        self.renderables.insert(name, renderable.clone());
+        renderable
+    }
+
+    pub fn remove_renderable(&mut self, name: &str) {
+        self.renderables.remove(name);
+    }
+
+    pub fn update_instances(&self, name: &str, instances: &[PointInstance]) {
+        match self.renderables.get(name) {
+            Some(renderable) => renderable.write_instances(&self.queue, instances),
+            None => warn!("renderable with name {} does not exist!", name),
+        }
+    }
+
+    pub fn update_renderable(&self, name: &str, vertices: &[Vertex], indices: &[u32]) {
+        match self.renderables.get(name) {
+            Some(renderable) => renderable.write(&self.queue, vertices, indices),
+            None => warn!("renderable with name {} does not exist!", name),
+        }
+    }
+
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
@@ -197,56 +394,85 @@ impl Renderer {
         let mut encoder = self
             .device
             .create_command_encoder(&CommandEncoderDescriptor::default());
-        // grid pass
-        {
-            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-                label: Some("Grid renderpass"),
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &self.render_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: wgpu::StoreOp::Store,
-                    },
-                    depth_slice: None,
-                })],
-                depth_stencil_attachment: None, // TODO: might need depth
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-            if let Some(pass)=  &self.grid_pass {
-                pass::draw_grid(&mut render_pass, pass);
+        let full_window = [View {
+            origin: Vec2::zero(),
+            size: Vec2::new(self.config.width, self.config.height).as_(),
+            buffers: Vec::new(),
+            hidden_passes: Vec::new(),
+            hidden_renderables: Vec::new(),
+        }];
+        let views = if self.views.is_empty() { &full_window[..] } else { &self.views[..] };
+        for (i, view) in views.iter().enumerate() {
+            // global buffers hold this view's data until the next view's copies
+            for (idx, staging) in self.view_buffers.get(i).into_iter().flatten() {
+                encoder.copy_buffer_to_buffer(staging, 0, &self.global_buffers[*idx], 0, staging.size());
             }
-        }        
-        // main pass
-        {
-            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-                label: Some("Main renderpass"),
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &self.render_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: wgpu::StoreOp::Store,
-                    },
-                    depth_slice: None,
-                })],
-                depth_stencil_attachment: None, // TODO: add depth
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
+            // keep the view inside the render texture, a zero sized viewport is invalid
+            let max = Vec2::new(self.config.width, self.config.height).as_::<f32>();
+            let origin = Vec2::partial_min(view.origin, max - 1.0).map(|x: f32| x.max(0.0));
+            let size = Vec2::partial_min(view.size, max - origin).map(|x: f32| x.max(1.0));
+            let set_viewport = |render_pass: &mut wgpu::RenderPass| {
+                render_pass.set_viewport(origin.x, origin.y, size.x, size.y, 0.0, 1.0);
+                render_pass.set_scissor_rect(origin.x as u32, origin.y as u32, size.x as u32, size.y as u32);
+            };
+            // grid pass
+            {
+                let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                    label: Some("Grid renderpass"),
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: &self.render_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            // clearing ignores the viewport, so only the first view clears
+                            load: if i == 0 { wgpu::LoadOp::Clear(wgpu::Color::BLACK) } else { wgpu::LoadOp::Load },
+                            store: wgpu::StoreOp::Store,
+                        },
+                        depth_slice: None,
+                    })],
+                    depth_stencil_attachment: None, // TODO: might need depth
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                set_viewport(&mut render_pass);
+                if let Some(pass)=  &self.grid_pass {
+                    pass::draw_grid(&mut render_pass, pass);
+                }
+            }
+            // main pass
+            {
+                let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                    label: Some("Main renderpass"),
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: &self.render_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                        depth_slice: None,
+                    })],
+                    depth_stencil_attachment: None, // TODO: add depth
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                });
+                set_viewport(&mut render_pass);
 
-            // issue drawcalls
-            for pass_name in self.passes.keys() {
-                let renderables = self
-                    .renderables
-                    .values()
-                    .filter(|x| &x.pass_name == pass_name);
-                if let Some(pass) = self.passes.get(pass_name) {
-                    // debug!("drawing {} renderables with pass {}", renderables.collect::<Vec<_>>().len(), pass_name);
-                    pass.draw(&mut render_pass, renderables)?;
-                } else {
-                    warn!("pass with name {} does not exist!", pass_name);
+                // issue drawcalls
+                for pass_name in self.passes.keys().filter(|name| !self.disabled_passes.contains(*name) && !view.hidden_passes.contains(*name)) {
+                    let renderables = self
+                        .renderables
+                        .iter()
+                        .filter(|(name, x)| &x.pass_name == pass_name && !view.hidden_renderables.contains(*name))
+                        .map(|(_, x)| x);
+                    if let Some(pass) = self.passes.get(pass_name) {
+                        // debug!("drawing {} renderables with pass {}", renderables.collect::<Vec<_>>().len(), pass_name);
+                        pass.draw(&mut render_pass, renderables)?;
+                    } else {
+                        warn!("pass with name {} does not exist!", pass_name);
+                    }
+                }
+                if let Some(pass) = &self.ifs_pass && self.ifs_point_count > 0 {
+                    pass::draw_ifs(&mut render_pass, pass, self.ifs_point_count);
                 }
             }
         }
@@ -0,0 +1,98 @@
+use wgpu::{
+    AddressMode, BindGroupEntry, BindGroupLayoutEntry, BindingResource, BindingType, Device, Extent3d, FilterMode, Origin3d, Queue, Sampler,
+    SamplerBindingType, SamplerDescriptor, ShaderStages, TexelCopyBufferLayout, TexelCopyTextureInfo, Texture, TextureAspect, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
+};
+
+// an rgba image bound to every pass, the texture at `binding` and its sampler at `binding + 1`
+pub struct GlobalTexture {
+    pub binding: u32,
+    texture: Texture,
+    view: TextureView,
+    sampler: Sampler,
+}
+
+impl GlobalTexture {
+    pub fn new(device: &Device, queue: &Queue, label: &str, binding: u32, width: u32, height: u32, rgba: &[u8]) -> Self {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some(label),
+            size: Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some(label),
+            address_mode_u: AddressMode::Repeat,
+            address_mode_v: AddressMode::Repeat,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+        let global_texture = Self { binding, texture, view, sampler };
+        global_texture.write(queue, rgba);
+        global_texture
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.texture.width(), self.texture.height())
+    }
+
+    // the data has to match the size of the texture
+    pub fn write(&self, queue: &Queue, rgba: &[u8]) {
+        let (width, height) = self.size();
+        queue.write_texture(
+            TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            rgba,
+            TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+    }
+
+    pub fn layout_entries(&self) -> [BindGroupLayoutEntry; 2] {
+        [
+            BindGroupLayoutEntry {
+                binding: self.binding,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: self.binding + 1,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                count: None,
+            },
+        ]
+    }
+
+    pub fn bind_group_entries(&self) -> [BindGroupEntry<'_>; 2] {
+        [
+            BindGroupEntry {
+                binding: self.binding,
+                resource: BindingResource::TextureView(&self.view),
+            },
+            BindGroupEntry {
+                binding: self.binding + 1,
+                resource: BindingResource::Sampler(&self.sampler),
+            },
+        ]
+    }
+}
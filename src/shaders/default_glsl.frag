@@ -2,10 +2,24 @@
 
 layout (location = 0) in vec4 in_uv;
 layout (location = 1) in vec3 inColor;
+layout (location = 2) in vec3 in_tint;
 
 layout (location = 0) out vec4 out_FragColor;
 
+layout (set = 0, binding = 5) uniform Texturing {
+    vec4 info; // enabled, unused, unused, unused
+} texturing;
+
+layout (set = 0, binding = 6) uniform texture2D model_texture;
+layout (set = 0, binding = 7) uniform sampler model_sampler;
+
 void main()
 {
-    out_FragColor = vec4(inColor, 1);
+    if (texturing.info.x > 0.5) {
+        // images are stored top row first, while v points up on the model
+        vec2 uv = vec2(in_uv.x, 1.0 - in_uv.y);
+        out_FragColor = vec4(texture(sampler2D(model_texture, model_sampler), uv).rgb * in_tint, 1);
+    } else {
+        out_FragColor = vec4(inColor, 1);
+    }
 }
@@ -2,9 +2,11 @@
 
 layout (location = 0) in vec4 in_position;
 layout (location = 1) in vec4 in_uv;
+layout (location = 2) in vec4 in_color;
 
 layout (location = 0) out vec4 out_uv;
 layout (location = 1) out vec3 out_color;
+layout (location = 2) out vec3 out_tint; // multiplies the texture when there is one
 
 layout (set = 0, binding = 0) uniform Camera {
     mat4 view_proj;
@@ -25,7 +27,18 @@ const vec3 colors[4] = vec3[4](
 void main() 
 {
     gl_Position = camera.view_proj * model.transform * in_position;
+    // there is no depth buffer, keep 3d shapes from being clipped by their depth
+    gl_Position.z = 0.5 * gl_Position.w;
     out_uv = in_uv;
-    out_color = colors[gl_VertexIndex % 4];
+    // blend the corner colours by uv, so subdivided meshes look like the plain quad
+    out_color = mix(mix(colors[0], colors[1], in_uv.x), mix(colors[2], colors[3], in_uv.x), in_uv.y);
+    // imported shapes bring their own colours
+    if (in_color.a > 0.0) {
+        out_color = in_color.rgb;
+    }
+    out_tint = in_color.a > 0.0 ? in_color.rgb : vec3(1);
+    // uv.z darkens parts of a shape drawn over the rest of it
+    out_color *= 1.0 - in_uv.z;
+    out_tint *= 1.0 - in_uv.z;
 }
 
@@ -15,6 +15,12 @@ layout (set = 0, binding = 2) uniform PanAndZoom {
     float aspect;
 } pan_and_zoom;
 
+layout (set = 0, binding = 4) uniform Basis {
+    mat4 to_basis; // world coordinates to coordinates in the custom basis
+    float enabled;
+    float line_scale; // roughly how much the basis stretches lengths, keeps lines at a constant width
+} basis;
+
 
 vec3 grid(vec2 position, float scale, float thickness, float level) {
     const float base_line_width = thickness;
@@ -28,13 +34,12 @@ vec3 grid(vec2 position, float scale, float thickness, float level) {
 }
 
 
-void main() {
+// levels of detail blended by zoom, with the x and y axes highlighted
+vec3 lattice(vec2 offset, float zoom) {
     const float LOGSCALE = 5.0;
     const float MAX_LINE_THICKNESS = 0.010;
 
-    vec2 offset = (inverse(camera.view_proj) * vec4(uv * 2 - 1, 0.0, 1.0)).xy; // TODO: optimize
-    
-    float grid_level = log(pan_and_zoom.zoom) / log(LOGSCALE) + 3.0 + 1.20; // [0, 4] for zoom [0.001, 10] + 1.2 for styling
+    float grid_level = log(zoom) / log(LOGSCALE) + 3.0 + 1.20; // [0, 4] for zoom [0.001, 10] + 1.2 for styling
     uint grid_floor = uint(floor(grid_level));
     uint grid_ceil = uint(ceil(grid_level));
     float t = grid_level - float(grid_floor);
@@ -43,15 +48,27 @@ void main() {
     float thickness1 = (t) * MAX_LINE_THICKNESS;
     float scale0 = pow(LOGSCALE, 3.0 - grid_floor);
     float scale1 = scale0 / LOGSCALE;
-    vec3 col0 = grid(offset, pan_and_zoom.zoom, thickness0, scale0);
-    vec3 col1 = grid(offset, pan_and_zoom.zoom, thickness1, scale1);
+    vec3 col0 = grid(offset, zoom, thickness0, scale0);
+    vec3 col1 = grid(offset, zoom, thickness1, scale1);
     vec3 col = mix(col0, col1, t);
     float thickness = max(thickness0, thickness1);
-    if (abs(offset.x) < thickness / pan_and_zoom.zoom) {
+    if (abs(offset.x) < thickness / zoom) {
         col.rb = vec2(0.0);
     }
-    if (abs(offset.y) < thickness / pan_and_zoom.zoom) {
+    if (abs(offset.y) < thickness / zoom) {
         col.gb = vec2(0.0);
     }
+    return col;
+}
+
+void main() {
+    vec2 offset = (inverse(camera.view_proj) * vec4(uv * 2 - 1, 0.0, 1.0)).xy; // TODO: optimize
+    vec3 col = lattice(offset, pan_and_zoom.zoom);
+    if (basis.enabled > 0.5) {
+        // the skewed lattice of the custom basis on top of a dimmed standard grid
+        vec2 skewed = (basis.to_basis * vec4(offset, 0.0, 1.0)).xy;
+        vec3 custom = lattice(skewed, pan_and_zoom.zoom * basis.line_scale) * vec3(0.6, 0.8, 1.0);
+        col = max(col * 0.3, custom);
+    }
     out_FragColor = vec4(col, 1.0);
 }
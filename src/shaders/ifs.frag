@@ -0,0 +1,10 @@
+#version 460
+
+layout (location = 0) in vec3 in_color;
+
+layout (location = 0) out vec4 out_FragColor;
+
+void main()
+{
+    out_FragColor = vec4(in_color, 1);
+}
@@ -0,0 +1,54 @@
+#version 460
+
+// chaos game for an iterated function system: every vertex runs its own random walk
+// through the maps and ends up somewhere on the attractor
+
+layout (location = 0) out vec3 out_color;
+
+layout (set = 0, binding = 0) uniform Camera {
+    mat4 view_proj;
+} camera;
+
+const uint MAX_MAPS = 16;
+
+layout (set = 0, binding = 3) uniform Ifs {
+    uvec4 info; // map count, iterations, unused, unused
+    vec4 cumulative[MAX_MAPS / 4]; // cumulative probabilities, four to a vec4
+    mat4 maps[MAX_MAPS];
+} ifs;
+
+// pcg hash
+uint hash(uint x) {
+    uint state = x * 747796405u + 2891336453u;
+    uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+float random(inout uint rng) {
+    rng = hash(rng);
+    return float(rng) / 4294967295.0;
+}
+
+vec3 hue(float h) {
+    return clamp(abs(mod(h * 6.0 + vec3(0.0, 4.0, 2.0), 6.0) - 3.0) - 1.0, 0.0, 1.0);
+}
+
+void main() {
+    uint rng = hash(uint(gl_VertexIndex));
+    vec4 p = vec4(random(rng) * 2.0 - 1.0, random(rng) * 2.0 - 1.0, 0.0, 1.0);
+    uint chosen = 0u;
+    for (uint i = 0u; i < ifs.info.y; i++) {
+        float r = random(rng);
+        uint m = 0u;
+        while (m + 1u < ifs.info.x && r > ifs.cumulative[m / 4u][m % 4u]) {
+            m++;
+        }
+        p = ifs.maps[m] * p;
+        p /= p.w;
+        chosen = m;
+    }
+    gl_Position = camera.view_proj * p;
+    gl_PointSize = 1.0;
+    // colour points by the last map applied, which shows the self-similar pieces
+    out_color = mix(vec3(1.0), hue(float(chosen) / float(max(ifs.info.x, 1u))), 0.6);
+}
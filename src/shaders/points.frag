@@ -0,0 +1,16 @@
+#version 460
+
+layout (location = 0) in vec2 in_offset;
+layout (location = 1) in vec3 in_color;
+
+layout (location = 0) out vec4 out_FragColor;
+
+void main()
+{
+    float distance = length(in_offset);
+    if (distance > 1.0) {
+        discard;
+    }
+    // a darker rim keeps overlapping points apart
+    out_FragColor = vec4(in_color * (distance > 0.75 ? 0.5 : 1.0), 1);
+}
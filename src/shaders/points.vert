@@ -0,0 +1,29 @@
+#version 460
+
+// the corners of a sprite quad, copied to every point of the cloud
+
+layout (location = 0) in vec4 in_position;
+layout (location = 3) in vec3 in_center;
+layout (location = 4) in float in_radius;
+layout (location = 5) in vec4 in_color;
+
+layout (location = 0) out vec2 out_offset;
+layout (location = 1) out vec3 out_color;
+
+layout (set = 0, binding = 0) uniform Camera {
+    mat4 view_proj;
+} camera;
+
+layout (set = 0, binding = 1) uniform Model {
+    mat4 transform;
+} model;
+
+void main()
+{
+    // only the centre is transformed, the sprite stays round and keeps its size
+    vec4 center = model.transform * vec4(in_center, 1.0);
+    gl_Position = camera.view_proj * (center / center.w + vec4(in_position.xy * in_radius, 0.0, 0.0));
+    gl_Position.z = 0.5 * gl_Position.w;
+    out_offset = in_position.xy;
+    out_color = in_color.rgb;
+}